@@ -0,0 +1,76 @@
+use edit_distance::edit_distance;
+use log::info;
+
+use crate::artifact::names::names;
+
+// Maps OCR output to the closest known string, so that one or two wrong
+// glyphs don't make a whole artifact unrecognizable. Only a unique nearest
+// candidate is accepted, ties are left as they are.
+
+// keep in sync with ArtifactStatKey::from_zh_cn
+const STAT_NAMES: &[&str] = &[
+    "治疗加成",
+    "暴击伤害",
+    "暴击率",
+    "攻击力",
+    "元素精通",
+    "元素充能效率",
+    "生命值",
+    "防御力",
+    "雷元素伤害加成",
+    "火元素伤害加成",
+    "水元素伤害加成",
+    "冰元素伤害加成",
+    "风元素伤害加成",
+    "岩元素伤害加成",
+    "草元素伤害加成",
+    "物理伤害加成",
+];
+
+// how many glyphs may be wrong in a string of the given length
+fn max_distance(len: usize) -> usize {
+    (len + 2) / 4
+}
+
+pub fn correct<'a, 'b: 'a, I>(s: &'a str, lexicon: I) -> Option<&'a str>
+where
+    I: IntoIterator<Item = &'b str> + Clone,
+{
+    if s.is_empty() {
+        return None;
+    }
+    if lexicon.clone().into_iter().any(|word| word == s) {
+        return Some(s);
+    }
+
+    let threshold = max_distance(s.chars().count());
+    let mut best: Option<&'b str> = None;
+    let mut best_distance = usize::MAX;
+    let mut tie = false;
+    for word in lexicon {
+        let d = edit_distance(s, word);
+        if d < best_distance {
+            best = Some(word);
+            best_distance = d;
+            tie = false;
+        } else if d == best_distance {
+            tie = true;
+        }
+    }
+
+    if best_distance > threshold || tie {
+        return None;
+    }
+    let word = best?;
+    info!("corrected `{}` to `{}`", s, word);
+    Some(word)
+}
+
+// the name itself if it can't be corrected
+pub fn correct_piece_name(name: &str) -> &str {
+    correct(name, names().piece_names()).unwrap_or(name)
+}
+
+pub fn correct_stat_name(name: &str) -> &str {
+    correct(name, STAT_NAMES.iter().cloned()).unwrap_or(name)
+}
@@ -0,0 +1,225 @@
+use crate::artifact::internal_artifact::{ArtifactStat, ArtifactStatKey};
+
+// The main-stat value shown in game is fixed by stat, rarity and level. Only
+// the level 0 and max level values are tabulated here, the levels in between
+// are estimated linearly from them, which can be off by a display step or two
+// compared to the game. A value that is clearly wrong is only corrected when
+// a typical misread (a lost decimal point or one wrong digit) explains it and
+// exactly one such fix fits, otherwise it is reported and kept as read.
+
+// (level 0, max level) of 3, 4 and 5 star artifacts
+fn ranges(key: &ArtifactStatKey) -> Option<[(f64, f64); 3]> {
+    use ArtifactStatKey::*;
+    let ranges = match key {
+        Hp => [(430.0, 1893.0), (645.0, 3571.0), (717.0, 4780.0)],
+        Atk => [(28.0, 123.0), (42.0, 232.0), (47.0, 311.0)],
+        HpPercentage | AtkPercentage | ElectroBonus | PyroBonus | HydroBonus | CryoBonus
+        | AnemoBonus | GeoBonus | DendroBonus => [(5.2, 23.1), (6.3, 34.8), (7.0, 46.6)],
+        DefPercentage | PhysicalBonus => [(6.6, 28.8), (7.9, 43.5), (8.7, 58.3)],
+        ElementalMastery => [(21.0, 92.0), (25.2, 139.0), (28.0, 186.5)],
+        Recharge => [(5.8, 25.7), (7.0, 38.7), (7.8, 51.8)],
+        Critical => [(3.5, 15.5), (4.2, 23.2), (4.7, 31.1)],
+        CriticalDamage => [(7.0, 31.0), (8.4, 46.4), (9.3, 62.2)],
+        HealingBonus => [(4.0, 17.9), (4.8, 26.8), (5.4, 35.9)],
+        Def => return None,
+    };
+    Some(ranges)
+}
+
+fn is_flat(key: &ArtifactStatKey) -> bool {
+    matches!(
+        key,
+        ArtifactStatKey::Hp
+            | ArtifactStatKey::Atk
+            | ArtifactStatKey::Def
+            | ArtifactStatKey::ElementalMastery
+    )
+}
+
+pub fn max_level(rarity: u32) -> Option<u32> {
+    match rarity {
+        3 => Some(12),
+        4 => Some(16),
+        5 => Some(20),
+        _ => None,
+    }
+}
+
+// Estimate of the value shown in game, None for rarities and stats without a
+// table.
+pub fn main_stat_value(key: &ArtifactStatKey, rarity: u32, level: u32) -> Option<f64> {
+    let max = max_level(rarity)?;
+    if level > max {
+        return None;
+    }
+    let (base, top) = ranges(key)?[rarity as usize - 3];
+    let value = base + (top - base) * level as f64 / max as f64;
+    let value = if is_flat(key) {
+        value.round()
+    } else {
+        (value * 10.0).round() / 10.0
+    };
+    Some(value)
+}
+
+fn format_value(key: &ArtifactStatKey, value: f64) -> String {
+    if is_flat(key) {
+        format!("{:.0}", value)
+    } else {
+        format!("{:.1}", value)
+    }
+}
+
+// how far the estimate may be from the value shown in game
+fn tolerance(key: &ArtifactStatKey) -> f64 {
+    if is_flat(key) {
+        3.0
+    } else {
+        0.3
+    }
+}
+
+pub enum MainStatCheck {
+    Match,
+    // the value the read one was corrected to
+    Corrected(f64),
+    Mismatch(String),
+}
+
+fn parse(digits: &str) -> Option<f64> {
+    if digits.starts_with('.') || digits.ends_with('.') {
+        return None;
+    }
+    digits.parse().ok()
+}
+
+// Values the text could have been before a misread: with the decimal point
+// put back anywhere close to the estimate, or with one digit replaced so that
+// it is the estimate exactly.
+fn candidates(key: &ArtifactStatKey, digits: &str, expected: f64) -> Vec<f64> {
+    let mut found: Vec<f64> = Vec::new();
+    let mut add = |value: f64| {
+        if !found.iter().any(|&v| (v - value).abs() < 1e-6) {
+            found.push(value);
+        }
+    };
+
+    if !is_flat(key) && !digits.contains('.') {
+        for i in 1..digits.len() {
+            let value = parse(&format!("{}.{}", &digits[..i], &digits[i..]));
+            if let Some(value) = value {
+                if (value - expected).abs() <= tolerance(key) + 1e-6 {
+                    add(value);
+                }
+            }
+        }
+    }
+
+    for (i, c) in digits.char_indices() {
+        if !c.is_ascii_digit() {
+            continue;
+        }
+        for d in b'0'..=b'9' {
+            let d = d as char;
+            if d == c {
+                continue;
+            }
+            let value = parse(&format!("{}{}{}", &digits[..i], d, &digits[i + 1..]));
+            if let Some(value) = value {
+                if (value - expected).abs() < 1e-6 {
+                    add(value);
+                }
+            }
+        }
+    }
+    found
+}
+
+// Checks the parsed main stat against the estimate, see the top of the file.
+pub fn validate(stat: &ArtifactStat, raw: &str, rarity: u32, level: u32) -> MainStatCheck {
+    let expected = match main_stat_value(&stat.key, rarity, level) {
+        Some(v) => v,
+        None => return MainStatCheck::Match,
+    };
+    if (stat.value - expected).abs() <= tolerance(&stat.key) + 1e-6 {
+        return MainStatCheck::Match;
+    }
+
+    let digits: String = raw.chars().filter(|&c| c != ',' && c != '%').collect();
+    let candidates = candidates(&stat.key, &digits, expected);
+    if let [value] = candidates[..] {
+        return MainStatCheck::Corrected(value);
+    }
+    MainStatCheck::Mismatch(format!(
+        "main stat {:?} `{}` does not match {} star +{} (expected about {}, {} corrections fit)",
+        stat.key,
+        raw,
+        rarity,
+        level,
+        format_value(&stat.key, expected),
+        candidates.len()
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn stat(key: ArtifactStatKey, value: f64) -> ArtifactStat {
+        ArtifactStat { key, value }
+    }
+
+    #[test]
+    fn max_level_values() {
+        assert_eq!(main_stat_value(&ArtifactStatKey::Hp, 5, 20), Some(4780.0));
+        assert_eq!(
+            main_stat_value(&ArtifactStatKey::CriticalDamage, 5, 20),
+            Some(62.2)
+        );
+        assert_eq!(main_stat_value(&ArtifactStatKey::Def, 5, 20), None);
+        assert_eq!(main_stat_value(&ArtifactStatKey::Hp, 5, 21), None);
+    }
+
+    fn check(key: ArtifactStatKey, raw: &str, level: u32) -> Option<f64> {
+        let value = raw.replace(&[',', '%'][..], "").parse().unwrap();
+        match validate(&stat(key, value), raw, 5, level) {
+            MainStatCheck::Match => Some(value),
+            MainStatCheck::Corrected(v) => Some(v),
+            MainStatCheck::Mismatch(_) => None,
+        }
+    }
+
+    #[test]
+    fn close_values_are_kept() {
+        assert_eq!(
+            check(ArtifactStatKey::CriticalDamage, "12.0%", 1),
+            Some(12.0)
+        );
+        assert_eq!(check(ArtifactStatKey::Hp, "4,779", 20), Some(4779.0));
+    }
+
+    #[test]
+    fn lost_decimal_point_is_put_back() {
+        assert_eq!(check(ArtifactStatKey::HpPercentage, "466%", 20), Some(46.6));
+        assert_eq!(
+            check(ArtifactStatKey::CriticalDamage, "622%", 20),
+            Some(62.2)
+        );
+    }
+
+    #[test]
+    fn one_misread_digit_is_fixed() {
+        assert_eq!(check(ArtifactStatKey::Hp, "4,180", 20), Some(4780.0));
+        assert_eq!(
+            check(ArtifactStatKey::AtkPercentage, "48.6%", 20),
+            Some(46.6)
+        );
+    }
+
+    #[test]
+    fn unexplained_values_are_reported() {
+        // two digits off
+        assert_eq!(check(ArtifactStatKey::Hp, "3,180", 20), None);
+        assert_eq!(check(ArtifactStatKey::CriticalDamage, "31.0%", 20), None);
+    }
+}
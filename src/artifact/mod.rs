@@ -1 +1,6 @@
-pub mod internal_artifact;
\ No newline at end of file
+pub mod correction;
+pub mod internal_artifact;
+pub mod main_stat;
+pub mod names;
+pub mod recognition_error;
+pub mod rolls;
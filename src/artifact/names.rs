@@ -0,0 +1,168 @@
+use anyhow::{anyhow, Result};
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+use std::sync::OnceLock;
+
+use log::info;
+use serde::Deserialize;
+
+use crate::artifact::internal_artifact::{ArtifactSetKey, ArtifactSlotKey, CharacterKey};
+
+// Chinese names of artifact pieces and characters. The bundled table lives in
+// names.json; an override file in the same format (json or yaml) can add or
+// replace entries, e.g. for renamed pieces. Sets and characters of a newer
+// game version can be added there by their GOOD key, they are kept as
+// `Other` and exported with that key.
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct PieceInfo {
+    pub set: ArtifactSetKey,
+    pub slot: ArtifactSlotKey,
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub struct NameDb {
+    #[serde(default)]
+    pieces: HashMap<String, PieceInfo>,
+    #[serde(default)]
+    characters: HashMap<String, CharacterKey>,
+}
+
+static NAMES: OnceLock<NameDb> = OnceLock::new();
+
+impl NameDb {
+    fn bundled() -> NameDb {
+        serde_json::from_str(include_str!("names.json")).expect("invalid bundled names.json")
+    }
+
+    pub fn from_file(path: &Path) -> Result<NameDb> {
+        let content = fs::read_to_string(path)
+            .map_err(|e| anyhow!("cannot read {}: {}", path.display(), e))?;
+        let is_yaml = matches!(path.extension(), Some(ext) if ext == "yaml" || ext == "yml");
+        let db = if is_yaml {
+            serde_yaml::from_str(&content).map_err(|e| anyhow!(e))
+        } else {
+            serde_json::from_str(&content).map_err(|e| anyhow!(e))
+        };
+        db.map_err(|e| anyhow!("{}: {}", path.display(), e))
+    }
+
+    fn merge(&mut self, other: NameDb) {
+        self.pieces.extend(other.pieces);
+        self.characters.extend(other.characters);
+    }
+
+    pub fn piece(&self, name: &str) -> Option<&PieceInfo> {
+        self.pieces.get(name)
+    }
+
+    pub fn character(&self, name: &str) -> Option<&CharacterKey> {
+        self.characters.get(name)
+    }
+
+    pub fn piece_names(&self) -> impl Iterator<Item = &str> + Clone {
+        self.pieces.keys().map(|s| s.as_str())
+    }
+
+    // any of the names of a piece, for drawing it
+    pub fn piece_name(&self, set: &ArtifactSetKey, slot: &ArtifactSlotKey) -> Option<&str> {
+        self.pieces
+            .iter()
+            .find(|(_, p)| p.set == *set && p.slot == *slot)
+            .map(|(name, _)| name.as_str())
+    }
+
+    pub fn has_set(&self, key: &str) -> bool {
+        self.pieces.values().any(|p| p.set.to_string() == key)
+    }
+
+    pub fn has_character(&self, key: &str) -> bool {
+        self.characters.values().any(|c| c.to_string() == key)
+    }
+
+    // sets and characters that this version has no key for
+    fn new_keys(&self) -> Vec<String> {
+        let sets = self
+            .pieces
+            .values()
+            .map(|p| &p.set)
+            .filter_map(|s| match s {
+                ArtifactSetKey::Other(key) => Some(key.clone()),
+                _ => None,
+            });
+        let characters = self.characters.values().filter_map(|c| match c {
+            CharacterKey::Other(key) => Some(key.clone()),
+            _ => None,
+        });
+        let mut keys: Vec<String> = sets.chain(characters).collect();
+        keys.sort();
+        keys.dedup();
+        keys
+    }
+
+    pub fn character_name(&self, character: &CharacterKey) -> Option<&str> {
+        self.characters
+            .iter()
+            .find(|(_, c)| *c == character)
+            .map(|(name, _)| name.as_str())
+    }
+}
+
+pub fn names() -> &'static NameDb {
+    NAMES.get_or_init(NameDb::bundled)
+}
+
+// Has to be called before the first lookup.
+pub fn install_override(path: &Path) -> Result<()> {
+    let mut db = NameDb::bundled();
+    let other = NameDb::from_file(path)?;
+    info!(
+        "loaded {} pieces and {} characters from {}",
+        other.pieces.len(),
+        other.characters.len(),
+        path.display()
+    );
+    let new_keys = other.new_keys();
+    if !new_keys.is_empty() {
+        info!("新增的套装和角色: {}", new_keys.join(", "));
+    }
+    db.merge(other);
+    NAMES
+        .set(db)
+        .map_err(|_| anyhow!("name database is already in use"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn override_adds_new_keys() {
+        let path = std::env::temp_dir().join(format!("yas_names_{}.yaml", std::process::id()));
+        fs::write(
+            &path,
+            "pieces:\n  新套装之花: { set: NewSet, slot: Flower }\n  \
+             角斗士的留恋: { set: GladiatorsFinale, slot: Flower }\n\
+             characters:\n  新角色: NewCharacter\n",
+        )
+        .unwrap();
+        let db = NameDb::from_file(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        let piece = db.piece("新套装之花").unwrap();
+        assert_eq!(piece.set, ArtifactSetKey::Other(String::from("NewSet")));
+        assert_eq!(piece.set.to_string(), "NewSet");
+        assert_eq!(
+            db.piece("角斗士的留恋").unwrap().set,
+            ArtifactSetKey::GladiatorsFinale
+        );
+        assert_eq!(
+            db.character("新角色"),
+            Some(&CharacterKey::Other(String::from("NewCharacter")))
+        );
+        assert!(db.has_set("NewSet") && db.has_character("NewCharacter"));
+        assert!(!db.has_set("Typo"));
+        assert_eq!(db.new_keys(), vec!["NewCharacter", "NewSet"]);
+    }
+}
@@ -0,0 +1,108 @@
+use std::collections::BTreeMap;
+use std::fmt;
+
+use log::warn;
+use serde::Serialize;
+use strum_macros::Display;
+
+// Why an OCR'd panel could not be turned into an InternalArtifact, or, for
+// the kinds that do not stop recognition (see
+// YasScanResult::to_internal_artifact_with_warnings), what was wrong with
+// it. `field` is the crop region the text came from.
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Display, Serialize)]
+#[strum(serialize_all = "snake_case")]
+#[serde(rename_all = "snake_case")]
+pub enum RecognitionErrorKind {
+    UnknownSet,
+    UnknownSlot,
+    BadLevel,
+    StatParse,
+    UnknownCharacter,
+    MainStatMismatch,
+    MainStatCorrected,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct RecognitionError {
+    pub field: &'static str,
+    pub raw: String,
+    pub kind: RecognitionErrorKind,
+}
+
+impl RecognitionError {
+    pub fn new(field: &'static str, raw: &str, kind: RecognitionErrorKind) -> RecognitionError {
+        RecognitionError {
+            field,
+            raw: raw.to_string(),
+            kind,
+        }
+    }
+}
+
+impl fmt::Display for RecognitionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {} `{}`", self.field, self.kind, self.raw)
+    }
+}
+
+impl std::error::Error for RecognitionError {}
+
+#[derive(Debug, Default, Serialize)]
+struct SummaryEntry {
+    count: usize,
+    // artifact indices and raw texts of the first few errors
+    examples: Vec<(usize, String)>,
+}
+
+// Errors of a scan grouped by field and kind, so that a broken crop region
+// or name table stands out.
+#[derive(Debug, Default)]
+pub struct ErrorSummary {
+    entries: BTreeMap<(&'static str, RecognitionErrorKind), SummaryEntry>,
+}
+
+const MAX_EXAMPLES: usize = 3;
+
+impl ErrorSummary {
+    pub fn add(&mut self, index: usize, error: &RecognitionError) {
+        let entry = self.entries.entry((error.field, error.kind)).or_default();
+        entry.count += 1;
+        if entry.examples.len() < MAX_EXAMPLES {
+            entry.examples.push((index, error.raw.clone()));
+        }
+    }
+
+    pub fn total(&self) -> usize {
+        self.entries.values().map(|e| e.count).sum()
+    }
+
+    pub fn log(&self) {
+        for ((field, kind), entry) in self.entries.iter() {
+            warn!(
+                "{} {}: {} times, e.g. {:?}",
+                field, kind, entry.count, entry.examples
+            );
+        }
+    }
+}
+
+impl Serialize for ErrorSummary {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        #[derive(Serialize)]
+        struct Row<'a> {
+            field: &'static str,
+            kind: RecognitionErrorKind,
+            #[serde(flatten)]
+            entry: &'a SummaryEntry,
+        }
+        serializer.collect_seq(self.entries.iter().map(|(&(field, kind), entry)| Row {
+            field,
+            kind,
+            entry,
+        }))
+    }
+}
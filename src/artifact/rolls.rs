@@ -0,0 +1,167 @@
+use serde::Serialize;
+
+use crate::artifact::internal_artifact::{ArtifactStat, ArtifactStatKey, InternalArtifact};
+
+// Every substat starts with one roll and gains one more at each upgrade
+// (every 4 levels). A roll adds 70%, 80%, 90% or 100% of the max roll value
+// of the rarity, so the displayed value tells which rolls are possible.
+
+const TIERS: [f64; 4] = [0.7, 0.8, 0.9, 1.0];
+
+// max roll value of 3, 4 and 5 star artifacts
+fn max_rolls(key: &ArtifactStatKey) -> Option<[f64; 3]> {
+    use ArtifactStatKey::*;
+    let values = match key {
+        Hp => [179.25, 239.0, 298.75],
+        Atk => [11.67, 15.56, 19.45],
+        Def => [13.89, 18.52, 23.15],
+        HpPercentage | AtkPercentage => [3.5, 4.66, 5.83],
+        DefPercentage => [4.37, 5.83, 7.29],
+        ElementalMastery => [13.99, 18.65, 23.31],
+        Recharge => [3.89, 5.18, 6.48],
+        Critical => [2.33, 3.11, 3.89],
+        CriticalDamage => [4.66, 6.22, 7.77],
+        _ => return None,
+    };
+    Some(values)
+}
+
+fn is_flat(key: &ArtifactStatKey) -> bool {
+    matches!(
+        key,
+        ArtifactStatKey::Hp
+            | ArtifactStatKey::Atk
+            | ArtifactStatKey::Def
+            | ArtifactStatKey::ElementalMastery
+    )
+}
+
+// number of substats at level 0, rarity - 2 or rarity - 1
+fn initial_sub_stats(rarity: u32) -> Option<(usize, usize)> {
+    match rarity {
+        3..=5 => Some((rarity as usize - 2, rarity as usize - 1)),
+        _ => None,
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct RollDecomposition {
+    pub rolls: u32,
+    // number of rolls of each tier, 70% to 100%
+    pub tiers: [u32; 4],
+}
+
+#[derive(Debug, Clone)]
+pub struct SubStatRolls {
+    pub key: ArtifactStatKey,
+    pub value: f64,
+    pub decompositions: Vec<RollDecomposition>,
+}
+
+impl SubStatRolls {
+    pub fn possible_rolls(&self) -> Vec<u32> {
+        let mut rolls: Vec<u32> = self.decompositions.iter().map(|d| d.rolls).collect();
+        rolls.dedup();
+        rolls
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct RollAnalysis {
+    pub upgrades: u32,
+    pub sub_stats: Vec<SubStatRolls>,
+    // false if no combination of rolls gives these substats at this level
+    pub consistent: bool,
+}
+
+// all multisets of `rolls` tiers, as counts per tier
+fn tier_counts(rolls: u32) -> Vec<[u32; 4]> {
+    let mut result = Vec::new();
+    for a in 0..=rolls {
+        for b in 0..=rolls - a {
+            for c in 0..=rolls - a - b {
+                result.push([a, b, c, rolls - a - b - c]);
+            }
+        }
+    }
+    result
+}
+
+fn decompose(stat: &ArtifactStat, max_roll: f64, max_count: u32) -> Vec<RollDecomposition> {
+    let tolerance = if is_flat(&stat.key) { 0.51 } else { 0.051 };
+    let mut result = Vec::new();
+    for rolls in 1..=max_count {
+        for tiers in tier_counts(rolls) {
+            let sum: f64 = tiers
+                .iter()
+                .zip(TIERS.iter())
+                .map(|(&n, t)| n as f64 * t * max_roll)
+                .sum();
+            if (sum - stat.value).abs() <= tolerance {
+                result.push(RollDecomposition { rolls, tiers });
+            }
+        }
+    }
+    result
+}
+
+impl InternalArtifact {
+    pub fn sub_stats(&self) -> Vec<&ArtifactStat> {
+        [
+            &self.sub_stat_1,
+            &self.sub_stat_2,
+            &self.sub_stat_3,
+            &self.sub_stat_4,
+        ]
+        .iter()
+        .filter_map(|s| s.as_ref())
+        .collect()
+    }
+
+    // None for rarities below 3, which have no roll table
+    pub fn analyze_rolls(&self) -> Option<RollAnalysis> {
+        let (min_initial, max_initial) = initial_sub_stats(self.rarity)?;
+        let index = self.rarity as usize - 3;
+        let upgrades = self.level.min(self.rarity * 4) / 4;
+
+        let sub_stats: Vec<SubStatRolls> = self
+            .sub_stats()
+            .into_iter()
+            .map(|stat| {
+                let decompositions = match max_rolls(&stat.key) {
+                    Some(max) => decompose(stat, max[index], upgrades + 1),
+                    None => Vec::new(),
+                };
+                SubStatRolls {
+                    key: stat.key.clone(),
+                    value: stat.value,
+                    decompositions,
+                }
+            })
+            .collect();
+
+        // reachable totals of rolls over all substats
+        let mut totals = vec![0u32];
+        for s in sub_stats.iter() {
+            let mut next: Vec<u32> = totals
+                .iter()
+                .flat_map(|t| s.possible_rolls().into_iter().map(move |r| t + r))
+                .collect();
+            next.sort_unstable();
+            next.dedup();
+            totals = next;
+        }
+
+        let count = sub_stats.len();
+        let consistent = (min_initial..=max_initial).any(|initial| {
+            let total = initial + upgrades as usize;
+            count == total.min(4) && totals.contains(&(total as u32))
+        });
+
+        Some(RollAnalysis {
+            upgrades,
+            sub_stats,
+            consistent,
+        })
+    }
+}
@@ -1,127 +1,17 @@
-#[cfg(windows)]
-extern crate winapi;
-use anyhow::{anyhow, Result};
-use std::mem::size_of;
-use std::ptr::null_mut;
-
-use winapi::ctypes::c_void;
-use winapi::shared::windef::{HBITMAP, HDC};
-use winapi::um::wingdi::{
-    BitBlt, CreateCompatibleBitmap, CreateCompatibleDC, DeleteObject, GetDIBits, GetObjectW,
-    SelectObject, BITMAP, BITMAPINFO, BITMAPINFOHEADER, BI_RGB, DIB_RGB_COLORS, SRCCOPY,
-};
-use winapi::um::winuser::{GetDC, ReleaseDC};
+use anyhow::Result;
 
 use image::ImageBuffer;
 
 use crate::common::color::Color;
 use crate::common::{PixelRect, RawCaptureImage};
+use crate::platform;
 
-#[cfg(windows)]
-unsafe fn unsafe_capture(rect: &PixelRect) -> Result<Vec<u8>> {
-    // SetThreadDpiAwarenessContext(DPI_AWARENESS_CONTEXT_SYSTEM_AWARE);
-
-    let dc_window: HDC = GetDC(null_mut());
-
-    let dc_mem: HDC = CreateCompatibleDC(dc_window);
-    if dc_mem.is_null() {
-        return Err(anyhow!("CreateCompatibleDC Failed"));
-    }
-
-    let hbm: HBITMAP = CreateCompatibleBitmap(dc_window, rect.width, rect.height);
-    if hbm.is_null() {
-        return Err(anyhow!("CreateCompatibleBitmap failed"));
-    }
-
-    SelectObject(dc_mem, hbm as *mut c_void);
-
-    let result = BitBlt(
-        dc_mem,
-        0,
-        0,
-        rect.width,
-        rect.height,
-        dc_window,
-        rect.left,
-        rect.top,
-        SRCCOPY,
-    );
-    if result == 0 {
-        return Err(anyhow!("BitBlt failed"));
-    }
-
-    let mut bitmap: BITMAP = BITMAP {
-        bmBits: 0 as *mut c_void,
-        bmBitsPixel: 0,
-        bmPlanes: 0,
-        bmWidthBytes: 0,
-        bmHeight: 0,
-        bmWidth: 0,
-        bmType: 0,
-    };
-    GetObjectW(
-        hbm as *mut c_void,
-        size_of::<BITMAP>() as i32,
-        (&mut bitmap) as *mut BITMAP as *mut c_void,
-    );
-    // println!("bitmap width: {}", bitmap.bmWidth);
-    // println!("bitmap height: {}", bitmap.bmHeight);
-    // println!("bitmap bits pixel: {}", bitmap.bmBitsPixel);
-
-    let mut bi: BITMAPINFOHEADER = BITMAPINFOHEADER {
-        biSize: size_of::<BITMAPINFOHEADER>() as u32,
-        biWidth: bitmap.bmWidth,
-        biHeight: bitmap.bmHeight,
-        biPlanes: 1,
-        biBitCount: 32,
-        biCompression: BI_RGB,
-        biSizeImage: 0,
-        biXPelsPerMeter: 0,
-        biYPelsPerMeter: 0,
-        biClrUsed: 0,
-        biClrImportant: 0,
-    };
-
-    let bitmap_size: usize = (((bitmap.bmWidth * 32 + 31) / 32) * 4 * bitmap.bmHeight) as usize;
-    // println!("bitmap size: {}", bitmap_size);
-    // let mut buffer: Vec<u8> = vec![0; bitmap_size];
-
-    // let h_dib = GlobalAlloc(GHND, bitmap_size);
-    // let lpbitmap = GlobalLock(h_dib);
-    // println!("bitmap {:p}", lpbitmap);
-    let mut buffer: Vec<u8> = vec![0; bitmap_size];
-
-    GetDIBits(
-        dc_window,
-        hbm,
-        0,
-        bitmap.bmHeight as u32,
-        // lpbitmap,
-        buffer.as_mut_ptr() as *mut c_void,
-        (&mut bi) as *mut BITMAPINFOHEADER as *mut BITMAPINFO,
-        DIB_RGB_COLORS,
-    );
-
-    // let buffer: Vec<u8> = Vec::from_raw_parts(lpbitmap as *mut u8, bitmap_size, bitmap_size);
-    // for i in 0..10 {
-    //     println!("{}", buffer[i]);
-    // }
-
-    // println!("{}", buffer[0]);
-
-    DeleteObject(hbm as *mut c_void);
-    DeleteObject(dc_mem as *mut c_void);
-    ReleaseDC(null_mut(), dc_window);
-
-    Ok(buffer)
-}
+pub mod screen_source;
 
-#[cfg(windows)]
 pub fn capture_absolute(rect: &PixelRect) -> Result<Vec<u8>> {
-    unsafe { unsafe_capture(&rect) }
+    platform::capture_absolute(rect)
 }
 
-#[cfg(windows)]
 pub fn capture_absolute_raw_image(rect: &PixelRect) -> Result<RawCaptureImage> {
     let pixels = capture_absolute(&rect)?;
     Ok(RawCaptureImage {
@@ -131,7 +21,6 @@ pub fn capture_absolute_raw_image(rect: &PixelRect) -> Result<RawCaptureImage> {
     })
 }
 
-#[cfg(windows)]
 pub fn capture_absolute_image(rect: &PixelRect) -> Result<image::RgbImage> {
     let raw: Vec<u8> = match capture_absolute(rect) {
         Err(s) => {
@@ -154,7 +43,6 @@ pub fn capture_absolute_image(rect: &PixelRect) -> Result<image::RgbImage> {
     Ok(img)
 }
 
-#[cfg(windows)]
 pub fn get_color(x: u32, y: u32) -> Result<Color> {
     let im = capture_absolute(&PixelRect {
         left: x as i32,
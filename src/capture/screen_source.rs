@@ -0,0 +1,256 @@
+use anyhow::{anyhow, Result};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+#[cfg(windows)]
+use dxgcap::DXGIManager;
+use log::info;
+
+use crate::capture::capture_absolute_raw_image;
+use crate::common::{PixelRect, RawCaptureImage};
+
+// Where YasScanner gets its pixels from. Every rect passed in is in absolute
+// screen coordinates, and the returned image has the size of the rect.
+pub trait ScreenSource {
+    fn capture(&mut self, rect: &PixelRect) -> Result<RawCaptureImage>;
+}
+
+pub struct GdiSource;
+
+impl GdiSource {
+    pub fn new() -> GdiSource {
+        GdiSource
+    }
+}
+
+impl Default for GdiSource {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ScreenSource for GdiSource {
+    fn capture(&mut self, rect: &PixelRect) -> Result<RawCaptureImage> {
+        capture_absolute_raw_image(rect)
+    }
+}
+
+#[cfg(windows)]
+pub struct DxgiSource {
+    dxg: DXGIManager,
+}
+
+#[cfg(windows)]
+impl DxgiSource {
+    pub fn new() -> Result<DxgiSource> {
+        let mut dxg = DXGIManager::new(1000).map_err(|s| anyhow!(s))?;
+        // dxg的第一张截图可能是黑屏
+        dxg.capture_frame()
+            .map_err(|e| anyhow!("dxg capture init err: {:?}", e))?;
+        Ok(DxgiSource { dxg })
+    }
+}
+
+#[cfg(windows)]
+impl ScreenSource for DxgiSource {
+    fn capture(&mut self, rect: &PixelRect) -> Result<RawCaptureImage> {
+        let (pixels, (w, _)) = self
+            .dxg
+            .capture_frame()
+            .map_err(|e| anyhow!("dxg capture err: {:?}", e))?;
+
+        let mut im = RawCaptureImage {
+            data: vec![0; (rect.width * rect.height * 4) as usize],
+            w: rect.width as u32,
+            h: rect.height as u32,
+        };
+
+        for x in rect.left..rect.left + rect.width {
+            for y in rect.top..rect.top + rect.height {
+                let p = (y * w as i32 + x) as usize;
+                let pos = ((rect.height - 1 - (y - rect.top)) * rect.width + (x - rect.left))
+                    as usize
+                    * 4;
+                im.data[pos + 0] = pixels[p].b;
+                im.data[pos + 1] = pixels[p].g;
+                im.data[pos + 2] = pixels[p].r;
+                im.data[pos + 3] = pixels[p].a;
+            }
+        }
+        Ok(im)
+    }
+}
+
+// Replays a session recorded by RecordingSource. The directory holds
+// `window.json` (the client rect at recording time) and one png per capture,
+// served in file name order. A frame larger than the requested rect is
+// treated as a window screenshot and cropped.
+pub struct FileSource {
+    frames: Vec<PathBuf>,
+    cursor: usize,
+    window: PixelRect,
+}
+
+impl FileSource {
+    pub fn new(dir: &Path) -> Result<FileSource> {
+        let window = Self::window_rect(dir)?;
+
+        let mut frames: Vec<PathBuf> = fs::read_dir(dir)?
+            .filter_map(|e| e.ok())
+            .map(|e| e.path())
+            .filter(|p| matches!(p.extension(), Some(ext) if ext == "png"))
+            .collect();
+        frames.sort();
+
+        if frames.is_empty() {
+            return Err(anyhow!("no png frames found in {}", dir.display()));
+        }
+        info!("replaying {} frames from {}", frames.len(), dir.display());
+
+        Ok(FileSource {
+            frames,
+            cursor: 0,
+            window,
+        })
+    }
+
+    pub fn window_rect(dir: &Path) -> Result<PixelRect> {
+        let path = dir.join("window.json");
+        let json_str =
+            fs::read_to_string(&path).map_err(|_| anyhow!("cannot read {}", path.display()))?;
+        Ok(serde_json::from_str(&json_str)?)
+    }
+}
+
+impl ScreenSource for FileSource {
+    fn capture(&mut self, rect: &PixelRect) -> Result<RawCaptureImage> {
+        let path = self
+            .frames
+            .get(self.cursor)
+            .ok_or_else(|| anyhow!("replay exhausted after {} frames", self.cursor))?;
+        self.cursor += 1;
+
+        let frame = RawCaptureImage::load(path)?;
+        if frame.w == rect.width as u32 && frame.h == rect.height as u32 {
+            return Ok(frame);
+        }
+
+        let left = rect.left - self.window.left;
+        let top = rect.top - self.window.top;
+        if left < 0
+            || top < 0
+            || left + rect.width > frame.w as i32
+            || top + rect.height > frame.h as i32
+        {
+            return Err(anyhow!(
+                "frame {} ({}x{}) does not cover {:?}",
+                path.display(),
+                frame.w,
+                frame.h,
+                rect
+            ));
+        }
+        frame.crop(&PixelRect {
+            left,
+            top,
+            width: rect.width,
+            height: rect.height,
+        })
+    }
+}
+
+// Saves every capture of the wrapped source, so that the session can be
+// replayed with FileSource later.
+pub struct RecordingSource {
+    inner: Box<dyn ScreenSource>,
+    dir: PathBuf,
+    count: u32,
+}
+
+impl RecordingSource {
+    pub fn new(
+        inner: Box<dyn ScreenSource>,
+        dir: &Path,
+        window: &PixelRect,
+    ) -> Result<RecordingSource> {
+        fs::create_dir_all(dir)?;
+        fs::write(dir.join("window.json"), serde_json::to_string(window)?)?;
+        Ok(RecordingSource {
+            inner,
+            dir: dir.to_path_buf(),
+            count: 0,
+        })
+    }
+}
+
+impl ScreenSource for RecordingSource {
+    fn capture(&mut self, rect: &PixelRect) -> Result<RawCaptureImage> {
+        let shot = self.inner.capture(rect)?;
+        let path = self.dir.join(format!("{:06}.png", self.count));
+        shot.save(
+            path.to_str()
+                .ok_or_else(|| anyhow!("invalid record path"))?,
+        )?;
+        self.count += 1;
+        Ok(shot)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::common::color::Color;
+
+    // pixel (x, y) of the screen has the colour (x, y, 7)
+    struct Gradient;
+
+    impl ScreenSource for Gradient {
+        fn capture(&mut self, rect: &PixelRect) -> Result<RawCaptureImage> {
+            let (w, h) = (rect.width as u32, rect.height as u32);
+            let mut im = RawCaptureImage {
+                data: vec![255; (w * h * 4) as usize],
+                w,
+                h,
+            };
+            for y in 0..h {
+                for x in 0..w {
+                    let color = Color((rect.left + x as i32) as u8, (rect.top + y as i32) as u8, 7);
+                    im.set_color(x, y, &color)?;
+                }
+            }
+            Ok(im)
+        }
+    }
+
+    fn rect(left: i32, top: i32, width: i32, height: i32) -> PixelRect {
+        PixelRect {
+            left,
+            top,
+            width,
+            height,
+        }
+    }
+
+    #[test]
+    fn replay_recorded_pngs() {
+        let dir = std::env::temp_dir().join(format!("yas_replay_{}", std::process::id()));
+        let window = rect(100, 50, 40, 30);
+        let mut recorder = RecordingSource::new(Box::new(Gradient), &dir, &window).unwrap();
+        recorder.capture(&window).unwrap();
+        recorder.capture(&rect(110, 60, 10, 10)).unwrap();
+
+        let mut replay = FileSource::new(&dir).unwrap();
+        // a window screenshot is cropped to the requested rect
+        let shot = replay.capture(&rect(105, 56, 8, 4)).unwrap();
+        assert_eq!((shot.w, shot.h), (8, 4));
+        let c = shot.get_color(2, 3).unwrap();
+        assert_eq!((c.0, c.1, c.2), (107, 59, 7));
+        // a frame of the right size is served as is
+        let shot = replay.capture(&rect(110, 60, 10, 10)).unwrap();
+        let c = shot.get_color(0, 0).unwrap();
+        assert_eq!((c.0, c.1, c.2), (110, 60, 7));
+        assert!(replay.capture(&window).is_err());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
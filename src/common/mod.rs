@@ -4,6 +4,8 @@ use crate::info::info::ScanInfo;
 use anyhow::{anyhow, Result};
 use image::{GrayImage, ImageBuffer, ImageResult, RgbImage};
 use log::info;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
 use std::time::SystemTime;
 
 pub mod color;
@@ -11,7 +13,7 @@ pub mod utils;
 
 use color::Color;
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct PixelRect {
     pub left: i32,
     pub top: i32,
@@ -131,6 +133,18 @@ impl RawImage {
 }
 
 impl RawCaptureImage {
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<RawCaptureImage> {
+        let img = image::open(path)?.to_rgb8();
+        let (w, h) = img.dimensions();
+        let mut data = vec![255; (w * h * 4) as usize];
+        for (x, y, pixel) in img.enumerate_pixels() {
+            let p = ((h - 1 - y) * w + x) as usize * 4;
+            data[p] = pixel.0[2];
+            data[p + 1] = pixel.0[1];
+            data[p + 2] = pixel.0[0];
+        }
+        Ok(RawCaptureImage { data, w, h })
+    }
     pub fn save(&self, path: &str) -> ImageResult<()> {
         let data = &self.data;
 
@@ -172,6 +186,24 @@ impl RawCaptureImage {
         // im.to_gray_image().save("test.png");
         im
     }
+    pub fn crop(&self, rect: &PixelRect) -> Result<RawCaptureImage> {
+        if rect.left < 0
+            || rect.top < 0
+            || rect.left + rect.width > self.w as i32
+            || rect.top + rect.height > self.h as i32
+        {
+            return Err(anyhow!("Invalid cropping area"));
+        }
+        let (w, h) = (rect.width as u32, rect.height as u32);
+        let mut data = vec![0; (w * h * 4) as usize];
+        for y in 0..h {
+            // rows are stored bottom-up
+            let src = ((self.h - 1 - (y + rect.top as u32)) * self.w + rect.left as u32) as usize * 4;
+            let dst = ((h - 1 - y) * w) as usize * 4;
+            data[dst..dst + w as usize * 4].copy_from_slice(&self.data[src..src + w as usize * 4]);
+        }
+        Ok(RawCaptureImage { data, w, h })
+    }
     pub fn get_color(&self, x: u32, y: u32) -> Result<Color> {
         if x >= self.w || y >= self.h {
             return Err(anyhow!("Pixel coord out of bounds"));
@@ -0,0 +1,114 @@
+use anyhow::Result;
+
+use crate::artifact::internal_artifact::{ArtifactStat, InternalArtifact};
+use crate::expo::Exporter;
+
+// One row per artifact, keys are the GOOD ones. Keys added by a --names file
+// can be anything, so cells are quoted where needed.
+
+const HEADER: &str = "index,set,slot,rarity,level,lock,location,main_stat_key,main_stat_value,\
+sub_stat_1_key,sub_stat_1_value,sub_stat_2_key,sub_stat_2_value,\
+sub_stat_3_key,sub_stat_3_value,sub_stat_4_key,sub_stat_4_value";
+
+fn cell(s: String) -> String {
+    if s.contains(&[',', '"', '\n', '\r'][..]) {
+        format!("\"{}\"", s.replace('"', "\"\""))
+    } else {
+        s
+    }
+}
+
+fn stat_cells(stat: Option<&ArtifactStat>) -> [String; 2] {
+    match stat {
+        Some(s) => [s.key.to_good(), s.value.to_string()],
+        None => [String::new(), String::new()],
+    }
+}
+
+fn csv_row(index: usize, artifact: &InternalArtifact) -> String {
+    let mut cells = vec![
+        index.to_string(),
+        artifact.set_key.to_good(),
+        artifact.slot_key.to_good(),
+        artifact.rarity.to_string(),
+        artifact.level.to_string(),
+        artifact.lock.to_string(),
+        match &artifact.location {
+            Some(c) => c.to_good(),
+            None => String::new(),
+        },
+    ];
+    cells.extend(stat_cells(Some(&artifact.main_stat)));
+    for stat in [
+        &artifact.sub_stat_1,
+        &artifact.sub_stat_2,
+        &artifact.sub_stat_3,
+        &artifact.sub_stat_4,
+    ] {
+        cells.extend(stat_cells(stat.as_ref()));
+    }
+    cells.into_iter().map(cell).collect::<Vec<_>>().join(",")
+}
+
+pub struct CsvExporter;
+
+impl Exporter for CsvExporter {
+    fn name(&self) -> &'static str {
+        "csv"
+    }
+
+    fn extension(&self) -> &'static str {
+        "csv"
+    }
+
+    fn check(&self, _artifact: &InternalArtifact) -> Result<Option<String>> {
+        Ok(None)
+    }
+
+    fn serialize(&self, artifacts: &[InternalArtifact], indices: &[usize]) -> Result<String> {
+        let mut out = String::from(HEADER);
+        out.push('\n');
+        for (artifact, &index) in artifacts.iter().zip(indices.iter()) {
+            out.push_str(&csv_row(index, artifact));
+            out.push('\n');
+        }
+        Ok(out)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::artifact::internal_artifact::{
+        ArtifactSetKey, ArtifactSlotKey, ArtifactStatKey, CharacterKey,
+    };
+
+    #[test]
+    fn rows() {
+        let stat = |key, value| ArtifactStat { key, value };
+        let artifact = InternalArtifact {
+            set_key: ArtifactSetKey::Other(String::from("New, \"Set\"")),
+            slot_key: ArtifactSlotKey::Sands,
+            rarity: 5,
+            level: 20,
+            lock: true,
+            location: Some(CharacterKey::Bennett),
+            main_stat: stat(ArtifactStatKey::Recharge, 51.8),
+            sub_stat_1: Some(stat(ArtifactStatKey::CriticalDamage, 7.8)),
+            sub_stat_2: Some(stat(ArtifactStatKey::Atk, 19.0)),
+            sub_stat_3: None,
+            sub_stat_4: None,
+        };
+        let csv = CsvExporter.serialize(&[artifact], &[7]).unwrap();
+        let lines: Vec<&str> = csv.lines().collect();
+
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[0], HEADER);
+        assert_eq!(HEADER.split(',').count(), 17);
+        assert_eq!(
+            lines[1],
+            "7,\"New, \"\"Set\"\"\",sands,5,20,true,Bennett,enerRech_,51.8,\
+             critDMG_,7.8,atk,19,,,,"
+        );
+    }
+}
@@ -1,8 +1,11 @@
 use crate::artifact::internal_artifact::{
     ArtifactSetKey, ArtifactSlotKey, ArtifactStat, ArtifactStatKey, InternalArtifact,
 };
-use anyhow::Result;
-use serde::ser::{Serialize, SerializeMap, Serializer};
+use crate::artifact::main_stat::main_stat_value;
+use crate::expo::Exporter;
+use anyhow::{anyhow, Result};
+use serde::ser::{Error, Serialize, SerializeMap, Serializer};
+use serde::Deserialize;
 
 struct GenmoArtifact<'a> {
     artifact: &'a InternalArtifact,
@@ -25,7 +28,10 @@ impl<'a> Serialize for GenmoArtifact<'a> {
 
         let artifact = &self.artifact;
         let mut root = serializer.serialize_map(Some(13))?;
-        root.serialize_entry("asKey", artifact.set_key.to_genmo())?;
+        let set_key = artifact.set_key.to_genmo().ok_or_else(|| {
+            S::Error::custom(format!("set {} is not supported", artifact.set_key))
+        })?;
+        root.serialize_entry("asKey", set_key)?;
         root.serialize_entry("rarity", &artifact.rarity)?;
         root.serialize_entry("slot", artifact.slot_key.to_genmo())?;
         root.serialize_entry("level", &artifact.level)?;
@@ -66,6 +72,32 @@ impl ArtifactStatKey {
             ArtifactStatKey::DendroBonus => "dendroDamage",
         }
     }
+
+    pub fn from_genmo(s: &str) -> Option<ArtifactStatKey> {
+        let key = match s {
+            "healing" => ArtifactStatKey::HealingBonus,
+            "critDamage" => ArtifactStatKey::CriticalDamage,
+            "critRate" => ArtifactStatKey::Critical,
+            "flatATK" => ArtifactStatKey::Atk,
+            "percentATK" => ArtifactStatKey::AtkPercentage,
+            "elementalMastery" => ArtifactStatKey::ElementalMastery,
+            "energyRecharge" => ArtifactStatKey::Recharge,
+            "percentHP" => ArtifactStatKey::HpPercentage,
+            "flatHP" => ArtifactStatKey::Hp,
+            "percentDEF" => ArtifactStatKey::DefPercentage,
+            "flatDEF" => ArtifactStatKey::Def,
+            "electroDamage" => ArtifactStatKey::ElectroBonus,
+            "pyroDamage" => ArtifactStatKey::PyroBonus,
+            "hydroDamage" => ArtifactStatKey::HydroBonus,
+            "cryoDamage" => ArtifactStatKey::CryoBonus,
+            "anemoDamage" => ArtifactStatKey::AnemoBonus,
+            "geoDamage" => ArtifactStatKey::GeoBonus,
+            "physicalDamage" => ArtifactStatKey::PhysicalBonus,
+            "dendroDamage" => ArtifactStatKey::DendroBonus,
+            _ => return None,
+        };
+        Some(key)
+    }
 }
 
 impl ArtifactSlotKey {
@@ -78,11 +110,23 @@ impl ArtifactSlotKey {
             ArtifactSlotKey::Circlet => "circlet",
         }
     }
+
+    pub fn from_genmo(s: &str) -> Option<ArtifactSlotKey> {
+        let key = match s {
+            "flower" => ArtifactSlotKey::Flower,
+            "plume" => ArtifactSlotKey::Plume,
+            "eon" => ArtifactSlotKey::Sands,
+            "goblet" => ArtifactSlotKey::Goblet,
+            "circlet" => ArtifactSlotKey::Circlet,
+            _ => return None,
+        };
+        Some(key)
+    }
 }
 
 impl ArtifactSetKey {
-    pub fn to_genmo(&self) -> &'static str {
-        match self {
+    pub fn to_genmo(&self) -> Option<&'static str> {
+        let key = match self {
             ArtifactSetKey::ArchaicPetra => "archaic_petra",
             ArtifactSetKey::HeartOfDepth => "heart_of_depth",
             ArtifactSetKey::BlizzardStrayer => "blizzard_walker",
@@ -128,15 +172,77 @@ impl ArtifactSetKey {
             ArtifactSetKey::MarechausseeHunter => "marechaussee_hunter",
             ArtifactSetKey::GoldenTroupe => "golden_troupe",
             ArtifactSetKey::SongOfDaysPast => "song_of_days_past",
-            ArtifactSetKey::NighttimeWhispersInTheEchoingWoods => "nighttime_whispers_in_the_echoing_woods",
+            ArtifactSetKey::NighttimeWhispersInTheEchoingWoods => {
+                "nighttime_whispers_in_the_echoing_woods"
+            }
             ArtifactSetKey::FragmentOfHarmonicWhimsy => "fragment_of_harmonic_whimsy",
             ArtifactSetKey::UnfinishedReverie => "unfinished_reverie",
 
             // Not supported by Mingyulab
-            ArtifactSetKey::Adventurer => unreachable!(),
-            ArtifactSetKey::LuckyDog => unreachable!(),
-            ArtifactSetKey::TravelingDoctor => unreachable!(),
-        }
+            ArtifactSetKey::Adventurer => return None,
+            ArtifactSetKey::LuckyDog => return None,
+            ArtifactSetKey::TravelingDoctor => return None,
+            // newer than this version
+            ArtifactSetKey::Other(_) => return None,
+        };
+        Some(key)
+    }
+
+    pub fn from_genmo(s: &str) -> Option<ArtifactSetKey> {
+        let key = match s {
+            "archaic_petra" => ArtifactSetKey::ArchaicPetra,
+            "heart_of_depth" => ArtifactSetKey::HeartOfDepth,
+            "blizzard_walker" => ArtifactSetKey::BlizzardStrayer,
+            "retracing_bolide" => ArtifactSetKey::RetracingBolide,
+            "noblesse_oblige" => ArtifactSetKey::NoblesseOblige,
+            "gladiators_finale" => ArtifactSetKey::GladiatorsFinale,
+            "maiden_beloved" => ArtifactSetKey::MaidenBeloved,
+            "viridescent_venerer" => ArtifactSetKey::ViridescentVenerer,
+            "lavawalker" => ArtifactSetKey::Lavawalker,
+            "crimson_witch_of_flames" => ArtifactSetKey::CrimsonWitchOfFlames,
+            "thundersoother" => ArtifactSetKey::Thundersoother,
+            "thundering_fury" => ArtifactSetKey::ThunderingFury,
+            "bloodstained_chivalry" => ArtifactSetKey::BloodstainedChivalry,
+            "wanderers_troupe" => ArtifactSetKey::WanderersTroupe,
+            "scholar" => ArtifactSetKey::Scholar,
+            "gambler" => ArtifactSetKey::Gambler,
+            "tiny_miracle" => ArtifactSetKey::TinyMiracle,
+            "martial_artist" => ArtifactSetKey::MartialArtist,
+            "brave_heart" => ArtifactSetKey::BraveHeart,
+            "resolution_of_sojourner" => ArtifactSetKey::ResolutionOfSojourner,
+            "defenders_will" => ArtifactSetKey::DefenderWill,
+            "berserker" => ArtifactSetKey::Berserker,
+            "instructor" => ArtifactSetKey::Instructor,
+            "the_exile" => ArtifactSetKey::Exile,
+            "prayers_of_wisdom" => ArtifactSetKey::PrayersForWisdom,
+            "prayers_of_springtime" => ArtifactSetKey::PrayersToSpringtime,
+            "prayers_of_illumination" => ArtifactSetKey::PrayersForIllumination,
+            "prayers_of_destiny" => ArtifactSetKey::PrayersForDestiny,
+            "pale_flame" => ArtifactSetKey::PaleFlame,
+            "tenacity_of_the_millelith" => ArtifactSetKey::TenacityOfTheMillelith,
+            "seal_of_insulation" => ArtifactSetKey::EmblemOfSeveredFate,
+            "reminiscence_of_shime" => ArtifactSetKey::ShimenawasReminiscence,
+            "husk_of_opulent_dreams" => ArtifactSetKey::HuskOfOpulentDreams,
+            "divine_chorus" => ArtifactSetKey::OceanHuedClam,
+            "vermillion_hereafter" => ArtifactSetKey::VermillionHereafter,
+            "echoes_of_an_offering" => ArtifactSetKey::EchoesOfAnOffering,
+            "deepwood_memories" => ArtifactSetKey::DeepwoodMemories,
+            "gilded_dreams" => ArtifactSetKey::GildedDreams,
+            "desert_pavilion_chronicle" => ArtifactSetKey::DesertPavilionChronicle,
+            "flower_of_paradise_lost" => ArtifactSetKey::FlowerOfParadiseLost,
+            "nymphs_dream" => ArtifactSetKey::NymphsDream,
+            "vourukashas_glow" => ArtifactSetKey::VourukashasGlow,
+            "marechaussee_hunter" => ArtifactSetKey::MarechausseeHunter,
+            "golden_troupe" => ArtifactSetKey::GoldenTroupe,
+            "song_of_days_past" => ArtifactSetKey::SongOfDaysPast,
+            "nighttime_whispers_in_the_echoing_woods" => {
+                ArtifactSetKey::NighttimeWhispersInTheEchoingWoods
+            }
+            "fragment_of_harmonic_whimsy" => ArtifactSetKey::FragmentOfHarmonicWhimsy,
+            "unfinished_reverie" => ArtifactSetKey::UnfinishedReverie,
+            _ => return None,
+        };
+        Some(key)
     }
 }
 
@@ -145,14 +251,10 @@ pub struct GenmoFormat<'a> {
 }
 
 impl<'a> GenmoFormat<'a> {
-    pub fn new(results: &'a Vec<InternalArtifact>) -> GenmoFormat {
+    pub fn new(results: &'a [InternalArtifact]) -> GenmoFormat<'a> {
         let artifacts: Vec<GenmoArtifact<'a>> = results
-            .into_iter()
-            .filter(|artifact| {
-                artifact.set_key != ArtifactSetKey::Adventurer
-                    && artifact.set_key != ArtifactSetKey::LuckyDog
-                    && artifact.set_key != ArtifactSetKey::TravelingDoctor
-            })
+            .iter()
+            .filter(|artifact| artifact.set_key.to_genmo().is_some())
             .map(|artifact| GenmoArtifact { artifact })
             .collect();
         GenmoFormat { artifacts }
@@ -167,3 +269,153 @@ impl<'a> Serialize for GenmoFormat<'a> {
         serializer.serialize_some(&self.artifacts)
     }
 }
+
+// import
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct GenmoArtifactData {
+    as_key: String,
+    rarity: u32,
+    slot: String,
+    level: u32,
+    main_stat: String,
+    sub_stat1_type: String,
+    sub_stat1_value: f64,
+    sub_stat2_type: String,
+    sub_stat2_value: f64,
+    sub_stat3_type: String,
+    sub_stat3_value: f64,
+    sub_stat4_type: String,
+    sub_stat4_value: f64,
+}
+
+// missing substats are written as flatATK 0
+fn stat_from_genmo(key: &str, value: f64) -> Result<Option<ArtifactStat>> {
+    if value == 0.0 {
+        return Ok(None);
+    }
+    let key = ArtifactStatKey::from_genmo(key).ok_or_else(|| anyhow!("unknown stat `{}`", key))?;
+    Ok(Some(ArtifactStat { key, value }))
+}
+
+fn artifact_from_genmo(data: &GenmoArtifactData) -> Result<InternalArtifact> {
+    let set_key = ArtifactSetKey::from_genmo(&data.as_key)
+        .ok_or_else(|| anyhow!("unknown asKey `{}`", data.as_key))?;
+    let slot_key = ArtifactSlotKey::from_genmo(&data.slot)
+        .ok_or_else(|| anyhow!("unknown slot `{}`", data.slot))?;
+
+    // no main stat value in the format, it follows from rarity and level
+    let key = ArtifactStatKey::from_genmo(&data.main_stat)
+        .ok_or_else(|| anyhow!("unknown mainStat `{}`", data.main_stat))?;
+    let value = main_stat_value(&key, data.rarity, data.level).ok_or_else(|| {
+        anyhow!(
+            "no main stat value for {} at rarity {} level {}",
+            data.main_stat,
+            data.rarity,
+            data.level
+        )
+    })?;
+
+    Ok(InternalArtifact {
+        set_key,
+        slot_key,
+        rarity: data.rarity,
+        level: data.level,
+        // not part of the format
+        lock: false,
+        location: None,
+        main_stat: ArtifactStat { key, value },
+        sub_stat_1: stat_from_genmo(&data.sub_stat1_type, data.sub_stat1_value)?,
+        sub_stat_2: stat_from_genmo(&data.sub_stat2_type, data.sub_stat2_value)?,
+        sub_stat_3: stat_from_genmo(&data.sub_stat3_type, data.sub_stat3_value)?,
+        sub_stat_4: stat_from_genmo(&data.sub_stat4_type, data.sub_stat4_value)?,
+    })
+}
+
+pub fn import_genmo(json_str: &str) -> Result<Vec<InternalArtifact>> {
+    let data: Vec<GenmoArtifactData> = serde_json::from_str(json_str)?;
+    data.iter()
+        .enumerate()
+        .map(|(i, a)| artifact_from_genmo(a).map_err(|e| anyhow!("artifact {}: {}", i, e)))
+        .collect()
+}
+
+pub struct GenmoExporter;
+
+impl Exporter for GenmoExporter {
+    fn name(&self) -> &'static str {
+        "genmo"
+    }
+
+    fn check(&self, artifact: &InternalArtifact) -> Result<Option<String>> {
+        match artifact.set_key.to_genmo() {
+            Some(_) => Ok(None),
+            None => Err(anyhow!("set {} is not supported", artifact.set_key)),
+        }
+    }
+
+    fn serialize(&self, artifacts: &[InternalArtifact], _indices: &[usize]) -> Result<String> {
+        Ok(serde_json::to_string(&GenmoFormat::new(artifacts))?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn artifacts() -> Vec<InternalArtifact> {
+        let stat = |key, value| Some(ArtifactStat { key, value });
+        vec![
+            InternalArtifact {
+                set_key: ArtifactSetKey::GladiatorsFinale,
+                slot_key: ArtifactSlotKey::Sands,
+                rarity: 5,
+                level: 20,
+                lock: false,
+                location: None,
+                main_stat: ArtifactStat {
+                    key: ArtifactStatKey::Recharge,
+                    value: 51.8,
+                },
+                sub_stat_1: stat(ArtifactStatKey::CriticalDamage, 7.8),
+                sub_stat_2: stat(ArtifactStatKey::Atk, 19.0),
+                sub_stat_3: stat(ArtifactStatKey::Critical, 3.5),
+                sub_stat_4: None,
+            },
+            InternalArtifact {
+                set_key: ArtifactSetKey::EmblemOfSeveredFate,
+                slot_key: ArtifactSlotKey::Flower,
+                rarity: 4,
+                level: 0,
+                lock: false,
+                location: None,
+                main_stat: ArtifactStat {
+                    key: ArtifactStatKey::Hp,
+                    value: 645.0,
+                },
+                sub_stat_1: stat(ArtifactStatKey::HpPercentage, 4.1),
+                sub_stat_2: None,
+                sub_stat_3: None,
+                sub_stat_4: None,
+            },
+        ]
+    }
+
+    #[test]
+    fn round_trip() {
+        let artifacts = artifacts();
+        let json = GenmoExporter.serialize(&artifacts, &[]).unwrap();
+        assert_eq!(import_genmo(&json).unwrap(), artifacts);
+    }
+
+    #[test]
+    fn main_stat_without_table_is_an_error() {
+        let json = r#"[{"asKey": "gladiators_finale", "rarity": 2, "slot": "flower", "level": 4,
+            "mainStat": "flatHP", "subStat1Type": "flatATK", "subStat1Value": 0,
+            "subStat2Type": "flatATK", "subStat2Value": 0, "subStat3Type": "flatATK",
+            "subStat3Value": 0, "subStat4Type": "flatATK", "subStat4Value": 0}]"#;
+        let err = import_genmo(json).unwrap_err().to_string();
+        assert!(err.contains("no main stat value"), "{}", err);
+    }
+}
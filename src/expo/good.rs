@@ -1,11 +1,19 @@
-use anyhow::Result;
+use anyhow::{anyhow, Result};
+use log::warn;
 use std::convert::From;
+use std::fs;
+use std::path::Path;
 
 use serde::ser::{Serialize, SerializeMap, Serializer};
+use serde::Deserialize;
 
 use crate::artifact::internal_artifact::{
     ArtifactSetKey, ArtifactSlotKey, ArtifactStat, ArtifactStatKey, CharacterKey, InternalArtifact,
 };
+use crate::artifact::main_stat::main_stat_value;
+use crate::artifact::names::names;
+use crate::artifact::rolls::{RollAnalysis, SubStatRolls};
+use crate::expo::Exporter;
 
 impl ArtifactStatKey {
     pub fn to_good(&self) -> String {
@@ -32,11 +40,45 @@ impl ArtifactStatKey {
         };
         String::from(temp)
     }
+
+    pub fn from_good(s: &str) -> Option<ArtifactStatKey> {
+        let key = match s {
+            "heal_" => ArtifactStatKey::HealingBonus,
+            "critDMG_" => ArtifactStatKey::CriticalDamage,
+            "critRate_" => ArtifactStatKey::Critical,
+            "atk" => ArtifactStatKey::Atk,
+            "atk_" => ArtifactStatKey::AtkPercentage,
+            "eleMas" => ArtifactStatKey::ElementalMastery,
+            "enerRech_" => ArtifactStatKey::Recharge,
+            "hp_" => ArtifactStatKey::HpPercentage,
+            "hp" => ArtifactStatKey::Hp,
+            "def_" => ArtifactStatKey::DefPercentage,
+            "def" => ArtifactStatKey::Def,
+            "electro_dmg_" => ArtifactStatKey::ElectroBonus,
+            "pyro_dmg_" => ArtifactStatKey::PyroBonus,
+            "hydro_dmg_" => ArtifactStatKey::HydroBonus,
+            "cryo_dmg_" => ArtifactStatKey::CryoBonus,
+            "anemo_dmg_" => ArtifactStatKey::AnemoBonus,
+            "geo_dmg_" => ArtifactStatKey::GeoBonus,
+            "physical_dmg_" => ArtifactStatKey::PhysicalBonus,
+            "dendro_dmg_" => ArtifactStatKey::DendroBonus,
+            _ => return None,
+        };
+        Some(key)
+    }
 }
 
 impl ArtifactSetKey {
     pub fn to_good(&self) -> String {
-        return self.to_string();
+        self.to_string()
+    }
+
+    // keys unknown to this version only if the name table has them
+    pub fn from_good(s: &str) -> Option<ArtifactSetKey> {
+        match ArtifactSetKey::from(s.to_string()) {
+            ArtifactSetKey::Other(_) if !names().has_set(s) => None,
+            key => Some(key),
+        }
     }
 }
 
@@ -51,16 +93,36 @@ impl ArtifactSlotKey {
         };
         String::from(temp)
     }
+
+    pub fn from_good(s: &str) -> Option<ArtifactSlotKey> {
+        let key = match s {
+            "flower" => ArtifactSlotKey::Flower,
+            "plume" => ArtifactSlotKey::Plume,
+            "sands" => ArtifactSlotKey::Sands,
+            "goblet" => ArtifactSlotKey::Goblet,
+            "circlet" => ArtifactSlotKey::Circlet,
+            _ => return None,
+        };
+        Some(key)
+    }
 }
 
 impl CharacterKey {
     pub fn to_good(&self) -> String {
         self.to_string()
     }
+
+    pub fn from_good(s: &str) -> Option<CharacterKey> {
+        match CharacterKey::from(s.to_string()) {
+            CharacterKey::Other(_) if !names().has_character(s) => None,
+            key => Some(key),
+        }
+    }
 }
 
 struct GoodArtifactStat<'a> {
     stat: &'a ArtifactStat,
+    rolls: Option<&'a SubStatRolls>,
 }
 
 impl<'a> Serialize for GoodArtifactStat<'a> {
@@ -68,15 +130,22 @@ impl<'a> Serialize for GoodArtifactStat<'a> {
     where
         S: Serializer,
     {
-        let mut root = serializer.serialize_map(Some(2))?;
+        let mut root = serializer.serialize_map(None)?;
         root.serialize_entry("key", &self.stat.key.to_good())?;
         root.serialize_entry("value", &self.stat.value)?;
+        // not part of GOOD, only with --good-rolls
+        if let Some(rolls) = self.rolls {
+            root.serialize_entry("rolls", &rolls.decompositions)?;
+        }
         root.end()
     }
 }
 
 struct GoodArtifact<'a> {
     artifact: &'a InternalArtifact,
+    rolls: Option<RollAnalysis>,
+    index: Option<usize>,
+    main_stat_value: bool,
 }
 
 impl<'a> Serialize for GoodArtifact<'a> {
@@ -84,7 +153,7 @@ impl<'a> Serialize for GoodArtifact<'a> {
     where
         S: Serializer,
     {
-        let mut root = serializer.serialize_map(Some(8))?;
+        let mut root = serializer.serialize_map(None)?;
 
         root.serialize_entry("setKey", &self.artifact.set_key.to_good())?;
         root.serialize_entry("slotKey", &self.artifact.slot_key.to_good())?;
@@ -97,20 +166,28 @@ impl<'a> Serialize for GoodArtifact<'a> {
         };
         root.serialize_entry("location", &location)?;
         root.serialize_entry("mainStatKey", &self.artifact.main_stat.key.to_good())?;
-        let mut substats: Vec<GoodArtifactStat> = vec![];
-        if let Some(ref s) = self.artifact.sub_stat_1 {
-            substats.push(GoodArtifactStat { stat: s });
+        // not part of GOOD, the value shown in game, so importing is lossless
+        if self.main_stat_value {
+            root.serialize_entry("mainStatValue", &self.artifact.main_stat.value)?;
         }
-        if let Some(ref s) = self.artifact.sub_stat_2 {
-            substats.push(GoodArtifactStat { stat: s });
-        }
-        if let Some(ref s) = self.artifact.sub_stat_3 {
-            substats.push(GoodArtifactStat { stat: s });
+        let substats: Vec<GoodArtifactStat> = self
+            .artifact
+            .sub_stats()
+            .into_iter()
+            .enumerate()
+            .map(|(i, stat)| GoodArtifactStat {
+                stat,
+                rolls: self.rolls.as_ref().map(|r| &r.sub_stats[i]),
+            })
+            .collect();
+        root.serialize_entry("substats", &substats)?;
+        if let Some(ref rolls) = self.rolls {
+            root.serialize_entry("validRolls", &rolls.consistent)?;
         }
-        if let Some(ref s) = self.artifact.sub_stat_4 {
-            substats.push(GoodArtifactStat { stat: s });
+        // not part of GOOD, the backpack index for lock.json
+        if let Some(index) = self.index {
+            root.serialize_entry("index", &index)?;
         }
-        root.serialize_entry("substats", &substats)?;
         root.end()
     }
 }
@@ -137,10 +214,15 @@ impl<'a> Serialize for GoodFormat<'a> {
 }
 
 impl<'a> GoodFormat<'a> {
-    pub fn new(results: &'a Vec<InternalArtifact>) -> GoodFormat {
+    pub fn new(results: &'a [InternalArtifact]) -> GoodFormat<'a> {
         let artifacts: Vec<GoodArtifact<'a>> = results
-            .into_iter()
-            .map(|artifact| GoodArtifact { artifact })
+            .iter()
+            .map(|artifact| GoodArtifact {
+                artifact,
+                rolls: None,
+                index: None,
+                main_stat_value: false,
+            })
             .collect();
 
         GoodFormat {
@@ -150,4 +232,286 @@ impl<'a> GoodFormat<'a> {
             artifacts,
         }
     }
+
+    // adds the roll decomposition of each substat (`rolls`) and whether the
+    // substats are possible at all (`validRolls`)
+    pub fn with_rolls(mut self, enabled: bool) -> GoodFormat<'a> {
+        if enabled {
+            for a in self.artifacts.iter_mut() {
+                a.rolls = a.artifact.analyze_rolls();
+            }
+        }
+        self
+    }
+
+    // adds the backpack index of each artifact (`index`). Unrecognized slots
+    // are left out, so the position in `artifacts` is not the index.
+    pub fn with_indices(mut self, indices: &[usize]) -> GoodFormat<'a> {
+        for (a, &index) in self.artifacts.iter_mut().zip(indices.iter()) {
+            a.index = Some(index);
+        }
+        self
+    }
+
+    // adds the main-stat value (`mainStatValue`), which GOOD leaves to be
+    // looked up from rarity and level
+    pub fn with_main_stat_values(mut self, enabled: bool) -> GoodFormat<'a> {
+        for a in self.artifacts.iter_mut() {
+            a.main_stat_value = enabled;
+        }
+        self
+    }
+}
+
+pub struct GoodExporter {
+    pub rolls: bool,
+    // `index` and `mainStatValue`
+    pub extra: bool,
+}
+
+impl Exporter for GoodExporter {
+    fn name(&self) -> &'static str {
+        "good"
+    }
+
+    fn check(&self, _artifact: &InternalArtifact) -> Result<Option<String>> {
+        Ok(None)
+    }
+
+    fn serialize(&self, artifacts: &[InternalArtifact], indices: &[usize]) -> Result<String> {
+        let mut good = GoodFormat::new(artifacts)
+            .with_rolls(self.rolls)
+            .with_main_stat_values(self.extra);
+        if self.extra {
+            good = good.with_indices(indices);
+        }
+        Ok(serde_json::to_string(&good)?)
+    }
+}
+
+// import
+
+#[derive(Deserialize)]
+struct GoodStatData {
+    key: String,
+    value: f64,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct GoodArtifactData {
+    set_key: String,
+    slot_key: String,
+    level: u32,
+    rarity: u32,
+    #[serde(default)]
+    lock: bool,
+    #[serde(default)]
+    location: String,
+    main_stat_key: String,
+    // written by yas-lock, other tools leave it out
+    main_stat_value: Option<f64>,
+    #[serde(default)]
+    substats: Vec<GoodStatData>,
+    // written by yas-lock, see GoodFormat::with_indices
+    index: Option<usize>,
+}
+
+#[derive(Deserialize)]
+struct GoodData {
+    format: String,
+    version: u32,
+    #[serde(default)]
+    artifacts: Vec<GoodArtifactData>,
+}
+
+fn stat_from_good(stat: &GoodStatData) -> Result<ArtifactStat> {
+    let key = ArtifactStatKey::from_good(&stat.key)
+        .ok_or_else(|| anyhow!("unknown stat key `{}`", stat.key))?;
+    Ok(ArtifactStat {
+        key,
+        value: stat.value,
+    })
+}
+
+fn artifact_from_good(data: &GoodArtifactData) -> Result<InternalArtifact> {
+    let set_key = ArtifactSetKey::from_good(&data.set_key)
+        .ok_or_else(|| anyhow!("unknown setKey `{}`", data.set_key))?;
+    let slot_key = ArtifactSlotKey::from_good(&data.slot_key)
+        .ok_or_else(|| anyhow!("unknown slotKey `{}`", data.slot_key))?;
+    let location = match data.location.as_str() {
+        "" => None,
+        s => {
+            let location = CharacterKey::from_good(s);
+            if location.is_none() {
+                warn!("unknown location `{}`, imported as not equipped", s);
+            }
+            location
+        }
+    };
+
+    // GOOD has no main stat value, without yas-lock's mainStatValue it is
+    // estimated from rarity and level, which may be off a little below max
+    // level
+    let key = ArtifactStatKey::from_good(&data.main_stat_key)
+        .ok_or_else(|| anyhow!("unknown mainStatKey `{}`", data.main_stat_key))?;
+    let value = match data.main_stat_value {
+        Some(value) => value,
+        None => main_stat_value(&key, data.rarity, data.level).ok_or_else(|| {
+            anyhow!(
+                "no mainStatValue and no table for {} at rarity {} level {}",
+                data.main_stat_key,
+                data.rarity,
+                data.level
+            )
+        })?,
+    };
+    let main_stat = ArtifactStat { key, value };
+
+    let mut substats = data.substats.iter().filter(|s| !s.key.is_empty());
+    let mut next = || substats.next().map(stat_from_good).transpose();
+
+    Ok(InternalArtifact {
+        set_key,
+        slot_key,
+        rarity: data.rarity,
+        level: data.level,
+        lock: data.lock,
+        location,
+        main_stat,
+        sub_stat_1: next()?,
+        sub_stat_2: next()?,
+        sub_stat_3: next()?,
+        sub_stat_4: next()?,
+    })
+}
+
+// Reads the artifacts of a GOOD v1 or v2 file, e.g. a good.json written by
+// a previous scan. Characters and weapons are ignored.
+pub fn import_good(json_str: &str) -> Result<Vec<InternalArtifact>> {
+    Ok(import_good_indexed(json_str)?
+        .into_iter()
+        .map(|(_, artifact)| artifact)
+        .collect())
+}
+
+// Like import_good, with the backpack index of each artifact if the file
+// has one (good.json written by yas-lock).
+pub fn import_good_indexed(json_str: &str) -> Result<Vec<(Option<usize>, InternalArtifact)>> {
+    let data: GoodData = serde_json::from_str(json_str)?;
+    if data.format != "GOOD" {
+        return Err(anyhow!("not a GOOD file: format `{}`", data.format));
+    }
+    if data.version != 1 && data.version != 2 {
+        return Err(anyhow!("unsupported GOOD version {}", data.version));
+    }
+
+    data.artifacts
+        .iter()
+        .enumerate()
+        .map(|(i, a)| {
+            artifact_from_good(a)
+                .map(|artifact| (a.index, artifact))
+                .map_err(|e| anyhow!("artifact {}: {}", i, e))
+        })
+        .collect()
+}
+
+pub fn import_good_file(path: &Path) -> Result<Vec<(Option<usize>, InternalArtifact)>> {
+    let json_str =
+        fs::read_to_string(path).map_err(|e| anyhow!("cannot read {}: {}", path.display(), e))?;
+    import_good_indexed(&json_str)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn artifacts() -> Vec<InternalArtifact> {
+        let stat = |key, value| Some(ArtifactStat { key, value });
+        vec![
+            InternalArtifact {
+                set_key: ArtifactSetKey::GladiatorsFinale,
+                slot_key: ArtifactSlotKey::Sands,
+                rarity: 5,
+                level: 7,
+                lock: true,
+                location: Some(CharacterKey::Bennett),
+                main_stat: ArtifactStat {
+                    key: ArtifactStatKey::Recharge,
+                    value: 22.7,
+                },
+                sub_stat_1: stat(ArtifactStatKey::CriticalDamage, 7.8),
+                sub_stat_2: stat(ArtifactStatKey::Atk, 19.0),
+                sub_stat_3: stat(ArtifactStatKey::Critical, 3.5),
+                sub_stat_4: None,
+            },
+            InternalArtifact {
+                set_key: ArtifactSetKey::GladiatorsFinale,
+                slot_key: ArtifactSlotKey::Flower,
+                rarity: 4,
+                level: 0,
+                lock: false,
+                location: None,
+                main_stat: ArtifactStat {
+                    key: ArtifactStatKey::Hp,
+                    value: 645.0,
+                },
+                sub_stat_1: stat(ArtifactStatKey::HpPercentage, 4.1),
+                sub_stat_2: None,
+                sub_stat_3: None,
+                sub_stat_4: None,
+            },
+        ]
+    }
+
+    #[test]
+    fn round_trip() {
+        let artifacts = artifacts();
+        let good = |artifacts| {
+            let good = GoodFormat::new(artifacts)
+                .with_indices(&[0, 3])
+                .with_main_stat_values(true);
+            serde_json::to_string(&good).unwrap()
+        };
+        let json = good(&artifacts);
+
+        let imported = import_good_indexed(&json).unwrap();
+        let indices: Vec<usize> = imported.iter().map(|(i, _)| i.unwrap()).collect();
+        let imported: Vec<InternalArtifact> = imported.into_iter().map(|(_, a)| a).collect();
+        assert_eq!(indices, vec![0, 3]);
+        assert_eq!(imported, artifacts);
+
+        assert_eq!(good(&imported), json);
+    }
+
+    #[test]
+    fn extra_fields_are_opt_in() {
+        let artifacts = artifacts();
+        let exporter = |extra| GoodExporter {
+            rolls: false,
+            extra,
+        };
+
+        let plain = exporter(false).serialize(&artifacts, &[0, 3]).unwrap();
+        assert!(!plain.contains("index") && !plain.contains("mainStatValue"));
+        let imported = import_good_indexed(&plain).unwrap();
+        assert!(imported.iter().all(|(i, _)| i.is_none()));
+
+        let extra = exporter(true).serialize(&artifacts, &[0, 3]).unwrap();
+        let imported = import_good_indexed(&extra).unwrap();
+        assert_eq!(imported[1].0, Some(3));
+        assert_eq!(imported[0].1.main_stat.value, 22.7);
+    }
+
+    #[test]
+    fn unknown_location_is_not_equipped() {
+        let json = r#"{"format": "GOOD", "version": 2, "artifacts": [{"setKey": "GladiatorsFinale",
+            "slotKey": "flower", "level": 20, "rarity": 5, "location": "SomeoneNew",
+            "mainStatKey": "hp", "substats": []}]}"#;
+        let imported = import_good(json).unwrap();
+        assert_eq!(imported[0].location, None);
+        // estimated without mainStatValue, exact at max level
+        assert_eq!(imported[0].main_stat.value, 4780.0);
+    }
 }
@@ -1,3 +1,281 @@
+use anyhow::{anyhow, Result};
+use std::fs;
+use std::path::{Component, Path};
+
+use log::{error, info, warn};
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::artifact::internal_artifact::InternalArtifact;
+use crate::common::utils;
+use crate::expo::csv::CsvExporter;
+use crate::expo::genmo::GenmoExporter;
+use crate::expo::good::GoodExporter;
+use crate::expo::mona::MonaExporter;
+
+pub mod csv;
 pub mod genmo;
 pub mod good;
 pub mod mona;
+
+// Reads a file written by any of the exporters, telling the format from the
+// json layout: GOOD has `"format": "GOOD"`, genmo is a bare array and mona
+// has one array per slot.
+pub fn import_file(path: &Path) -> Result<Vec<InternalArtifact>> {
+    let json_str =
+        fs::read_to_string(path).map_err(|e| anyhow!("cannot read {}: {}", path.display(), e))?;
+    let value: Value = serde_json::from_str(&json_str)?;
+
+    let (name, artifacts) = if value.is_array() {
+        ("genmo", genmo::import_genmo(&json_str)?)
+    } else if value.get("format").is_some() {
+        ("GOOD", good::import_good(&json_str)?)
+    } else if value.get("flower").is_some() {
+        ("mona", mona::import_mona(&json_str)?)
+    } else {
+        return Err(anyhow!("unknown format: {}", path.display()));
+    };
+    info!(
+        "loaded {} artifacts from {} ({})",
+        artifacts.len(),
+        path.display(),
+        name
+    );
+    Ok(artifacts)
+}
+
+// One output format. `check` is called for every artifact first: an error
+// skips the artifact, `Ok(Some(note))` exports it with a caveat. `serialize`
+// only gets the artifacts that were not skipped, with their backpack indices.
+pub trait Exporter {
+    fn name(&self) -> &'static str;
+
+    fn extension(&self) -> &'static str {
+        "json"
+    }
+
+    fn check(&self, artifact: &InternalArtifact) -> Result<Option<String>>;
+
+    fn serialize(&self, artifacts: &[InternalArtifact], indices: &[usize]) -> Result<String>;
+}
+
+// options some of the exporters take
+#[derive(Debug, Default, Clone)]
+pub struct ExportOptions {
+    pub good_rolls: bool,
+    pub good_extra: bool,
+}
+
+// every format that can be passed to --format
+pub const FORMATS: &[&str] = &["mona", "genmo", "good", "csv"];
+
+// written when no --format is given
+pub const DEFAULT_FORMATS: &[&str] = &["mona", "genmo", "good"];
+
+pub fn create_exporter(name: &str, options: &ExportOptions) -> Option<Box<dyn Exporter>> {
+    let exporter: Box<dyn Exporter> = match name {
+        "mona" => Box::new(MonaExporter),
+        "genmo" => Box::new(GenmoExporter),
+        "good" => Box::new(GoodExporter {
+            rolls: options.good_rolls,
+            extra: options.good_extra,
+        }),
+        "csv" => Box::new(CsvExporter),
+        _ => return None,
+    };
+    Some(exporter)
+}
+
+// An exporter and the file it writes to, relative to the output directory.
+pub struct ExportTarget {
+    pub exporter: Box<dyn Exporter>,
+    pub file: String,
+}
+
+impl ExportTarget {
+    pub fn new(exporter: Box<dyn Exporter>) -> ExportTarget {
+        let file = format!("{}.{}", exporter.name(), exporter.extension());
+        ExportTarget { exporter, file }
+    }
+
+    // `NAME` or `NAME=FILE`, e.g. `good=backup.json`
+    pub fn parse(spec: &str, options: &ExportOptions) -> Result<ExportTarget> {
+        let (name, file) = match spec.split_once('=') {
+            Some((name, file)) => (name, Some(file)),
+            None => (spec, None),
+        };
+        let exporter = create_exporter(name, options).ok_or_else(|| {
+            anyhow!(
+                "unknown format `{}`, expected one of {}",
+                name,
+                FORMATS.join(", ")
+            )
+        })?;
+        let mut target = ExportTarget::new(exporter);
+        if let Some(file) = file {
+            // also reachable from ScanReq, so it must stay inside output-dir
+            let inside = Path::new(file)
+                .components()
+                .all(|c| matches!(c, Component::Normal(_) | Component::CurDir));
+            if file.is_empty() || !inside {
+                return Err(anyhow!(
+                    "invalid file `{}` for format `{}`, must be a relative path inside output-dir",
+                    file,
+                    name
+                ));
+            }
+            target.file = file.to_string();
+        }
+        Ok(target)
+    }
+
+    // the default formats with their default file names if `specs` is empty
+    pub fn from_specs(specs: &[String], options: &ExportOptions) -> Result<Vec<ExportTarget>> {
+        if specs.is_empty() {
+            return Ok(DEFAULT_FORMATS
+                .iter()
+                .filter_map(|name| create_exporter(name, options))
+                .map(ExportTarget::new)
+                .collect());
+        }
+        specs
+            .iter()
+            .map(|spec| ExportTarget::parse(spec, options))
+            .collect()
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct ExportDiagnostic {
+    // backpack index
+    pub index: usize,
+    pub message: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ExportReport {
+    pub format: &'static str,
+    pub file: String,
+    pub exported: usize,
+    pub skipped: Vec<ExportDiagnostic>,
+    pub notes: Vec<ExportDiagnostic>,
+    // set when the file could not be written
+    pub error: Option<String>,
+}
+
+pub fn run_exporter(
+    target: &ExportTarget,
+    artifacts: &[InternalArtifact],
+    indices: &[usize],
+    output_dir: &Path,
+) -> ExportReport {
+    let exporter = target.exporter.as_ref();
+    let mut supported = Vec::new();
+    let mut supported_indices = Vec::new();
+    let mut skipped = Vec::new();
+    let mut notes = Vec::new();
+    for (artifact, &index) in artifacts.iter().zip(indices.iter()) {
+        match exporter.check(artifact) {
+            Ok(note) => {
+                if let Some(message) = note {
+                    notes.push(ExportDiagnostic { index, message });
+                }
+                supported.push(artifact.clone());
+                supported_indices.push(index);
+            }
+            Err(e) => skipped.push(ExportDiagnostic {
+                index,
+                message: e.to_string(),
+            }),
+        }
+    }
+
+    let error = exporter
+        .serialize(&supported, &supported_indices)
+        .and_then(|content| {
+            // the file may be in a subdirectory, e.g. backup/good.json
+            let path = output_dir.join(&target.file);
+            if let Some(parent) = path.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            Ok(fs::write(path, content)?)
+        })
+        .err()
+        .map(|e| e.to_string());
+
+    ExportReport {
+        format: exporter.name(),
+        file: target.file.clone(),
+        exported: supported.len(),
+        skipped,
+        notes,
+        error,
+    }
+}
+
+// Runs every exporter, a failing one does not stop the others. The reports
+// are written to export_summary.json next to the outputs. `indices` are the
+// backpack indices of `artifacts`.
+pub fn export_all(
+    targets: &[ExportTarget],
+    artifacts: &[InternalArtifact],
+    indices: &[usize],
+    output_dir: &Path,
+) -> Result<Vec<ExportReport>> {
+    let reports: Vec<ExportReport> = targets
+        .iter()
+        .map(|target| run_exporter(target, artifacts, indices, output_dir))
+        .collect();
+
+    for r in reports.iter() {
+        if let Some(e) = &r.error {
+            error!("{}导出失败: {}", r.file, e);
+        }
+        if !r.skipped.is_empty() || !r.notes.is_empty() {
+            warn!(
+                "{}: 跳过{}个不支持的圣遗物，{}个有警告，详见export_summary.json",
+                r.file,
+                r.skipped.len(),
+                r.notes.len()
+            );
+        }
+    }
+
+    utils::dump_json(&reports, output_dir.join("export_summary.json"))?;
+    Ok(reports)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn file_stays_in_output_dir() {
+        let options = ExportOptions::default();
+        let target = ExportTarget::parse("good=backup/good.json", &options).unwrap();
+        assert_eq!(target.file, "backup/good.json");
+        for spec in [
+            "good=../good.json",
+            "good=a/../../good.json",
+            "good=/tmp/good.json",
+            "good=",
+        ]
+        .iter()
+        {
+            assert!(ExportTarget::parse(spec, &options).is_err(), "{}", spec);
+        }
+    }
+
+    #[test]
+    fn nested_file_is_written() {
+        let dir = std::env::temp_dir().join(format!("yas_export_{}", std::process::id()));
+        let options = ExportOptions::default();
+        let target = ExportTarget::parse("good=backup/good.json", &options).unwrap();
+        let reports = export_all(&[target], &[], &[], &dir).unwrap();
+        let written = fs::read_to_string(dir.join("backup").join("good.json"));
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(reports[0].error, None);
+        assert!(written.unwrap().contains("GOOD"));
+    }
+}
@@ -1,11 +1,14 @@
-use anyhow::Result;
+use anyhow::{anyhow, Result};
 use std::convert::From;
 
 use serde::ser::{Serialize, SerializeMap, Serializer};
+use serde::Deserialize;
 
 use crate::artifact::internal_artifact::{
     ArtifactSetKey, ArtifactSlotKey, ArtifactStat, ArtifactStatKey, CharacterKey, InternalArtifact,
 };
+use crate::artifact::names::names;
+use crate::expo::Exporter;
 
 type MonaArtifact = InternalArtifact;
 
@@ -34,11 +37,44 @@ impl ArtifactStatKey {
         };
         String::from(temp)
     }
+
+    pub fn from_mona(s: &str) -> Option<ArtifactStatKey> {
+        let key = match s {
+            "cureEffect" => ArtifactStatKey::HealingBonus,
+            "criticalDamage" => ArtifactStatKey::CriticalDamage,
+            "critical" => ArtifactStatKey::Critical,
+            "attackStatic" => ArtifactStatKey::Atk,
+            "attackPercentage" => ArtifactStatKey::AtkPercentage,
+            "elementalMastery" => ArtifactStatKey::ElementalMastery,
+            "recharge" => ArtifactStatKey::Recharge,
+            "lifePercentage" => ArtifactStatKey::HpPercentage,
+            "lifeStatic" => ArtifactStatKey::Hp,
+            "defendPercentage" => ArtifactStatKey::DefPercentage,
+            "defendStatic" => ArtifactStatKey::Def,
+            "thunderBonus" => ArtifactStatKey::ElectroBonus,
+            "fireBonus" => ArtifactStatKey::PyroBonus,
+            "waterBonus" => ArtifactStatKey::HydroBonus,
+            "iceBonus" => ArtifactStatKey::CryoBonus,
+            "windBonus" => ArtifactStatKey::AnemoBonus,
+            "rockBonus" => ArtifactStatKey::GeoBonus,
+            "physicalBonus" => ArtifactStatKey::PhysicalBonus,
+            "dendroBonus" => ArtifactStatKey::DendroBonus,
+            _ => return None,
+        };
+        Some(key)
+    }
 }
 
 impl ArtifactSetKey {
+    // newer sets have no key of their own and are written with the GOOD key
     pub fn to_mona(&self) -> String {
-        let same = self.to_string();
+        match self.mona_key() {
+            Some(key) => String::from(key),
+            None => self.to_string(),
+        }
+    }
+
+    fn mona_key(&self) -> Option<&'static str> {
         let temp = match self {
             ArtifactSetKey::ArchaicPetra => "archaicPetra",
             ArtifactSetKey::HeartOfDepth => "heartOfDepth",
@@ -77,9 +113,70 @@ impl ArtifactSetKey {
             ArtifactSetKey::ShimenawasReminiscence => "shimenawaReminiscence",
             ArtifactSetKey::HuskOfOpulentDreams => "huskOfOpulentDreams",
             ArtifactSetKey::OceanHuedClam => "oceanHuedClam",
-            _ => same.as_str(),
+
+            // no mona key, written with the GOOD key
+            ArtifactSetKey::VermillionHereafter => return None,
+            ArtifactSetKey::EchoesOfAnOffering => return None,
+            ArtifactSetKey::DeepwoodMemories => return None,
+            ArtifactSetKey::GildedDreams => return None,
+            ArtifactSetKey::DesertPavilionChronicle => return None,
+            ArtifactSetKey::FlowerOfParadiseLost => return None,
+            ArtifactSetKey::NymphsDream => return None,
+            ArtifactSetKey::VourukashasGlow => return None,
+            ArtifactSetKey::MarechausseeHunter => return None,
+            ArtifactSetKey::GoldenTroupe => return None,
+            ArtifactSetKey::SongOfDaysPast => return None,
+            ArtifactSetKey::NighttimeWhispersInTheEchoingWoods => return None,
+            ArtifactSetKey::FragmentOfHarmonicWhimsy => return None,
+            ArtifactSetKey::UnfinishedReverie => return None,
+            ArtifactSetKey::Other(_) => return None,
         };
-        String::from(temp)
+        Some(temp)
+    }
+
+    pub fn from_mona(s: &str) -> Option<ArtifactSetKey> {
+        let key = match s {
+            "archaicPetra" => ArtifactSetKey::ArchaicPetra,
+            "heartOfDepth" => ArtifactSetKey::HeartOfDepth,
+            "blizzardStrayer" => ArtifactSetKey::BlizzardStrayer,
+            "retracingBolide" => ArtifactSetKey::RetracingBolide,
+            "noblesseOblige" => ArtifactSetKey::NoblesseOblige,
+            "gladiatorFinale" => ArtifactSetKey::GladiatorsFinale,
+            "maidenBeloved" => ArtifactSetKey::MaidenBeloved,
+            "viridescentVenerer" => ArtifactSetKey::ViridescentVenerer,
+            "lavaWalker" => ArtifactSetKey::Lavawalker,
+            "crimsonWitch" => ArtifactSetKey::CrimsonWitchOfFlames,
+            "thunderSmoother" => ArtifactSetKey::Thundersoother,
+            "thunderingFury" => ArtifactSetKey::ThunderingFury,
+            "bloodstainedChivalry" => ArtifactSetKey::BloodstainedChivalry,
+            "wandererTroupe" => ArtifactSetKey::WanderersTroupe,
+            "scholar" => ArtifactSetKey::Scholar,
+            "gambler" => ArtifactSetKey::Gambler,
+            "tinyMiracle" => ArtifactSetKey::TinyMiracle,
+            "martialArtist" => ArtifactSetKey::MartialArtist,
+            "braveHeart" => ArtifactSetKey::BraveHeart,
+            "resolutionOfSojourner" => ArtifactSetKey::ResolutionOfSojourner,
+            "defenderWill" => ArtifactSetKey::DefenderWill,
+            "berserker" => ArtifactSetKey::Berserker,
+            "instructor" => ArtifactSetKey::Instructor,
+            "exile" => ArtifactSetKey::Exile,
+            "adventurer" => ArtifactSetKey::Adventurer,
+            "luckyDog" => ArtifactSetKey::LuckyDog,
+            "travelingDoctor" => ArtifactSetKey::TravelingDoctor,
+            "prayersForWisdom" => ArtifactSetKey::PrayersForWisdom,
+            "prayersToSpringtime" => ArtifactSetKey::PrayersToSpringtime,
+            "prayersForIllumination" => ArtifactSetKey::PrayersForIllumination,
+            "prayersForDestiny" => ArtifactSetKey::PrayersForDestiny,
+            "paleFlame" => ArtifactSetKey::PaleFlame,
+            "tenacityOfTheMillelith" => ArtifactSetKey::TenacityOfTheMillelith,
+            "emblemOfSeveredFate" => ArtifactSetKey::EmblemOfSeveredFate,
+            "shimenawaReminiscence" => ArtifactSetKey::ShimenawasReminiscence,
+            "huskOfOpulentDreams" => ArtifactSetKey::HuskOfOpulentDreams,
+            "oceanHuedClam" => ArtifactSetKey::OceanHuedClam,
+            // newer sets are written with the GOOD key
+            _ => return ArtifactSetKey::from_good(s),
+        };
+        Some(key)
     }
 }
 
@@ -94,6 +191,18 @@ impl ArtifactSlotKey {
         };
         String::from(temp)
     }
+
+    pub fn from_mona(s: &str) -> Option<ArtifactSlotKey> {
+        let key = match s {
+            "flower" => ArtifactSlotKey::Flower,
+            "feather" => ArtifactSlotKey::Plume,
+            "sand" => ArtifactSlotKey::Sands,
+            "cup" => ArtifactSlotKey::Goblet,
+            "head" => ArtifactSlotKey::Circlet,
+            _ => return None,
+        };
+        Some(key)
+    }
 }
 
 impl CharacterKey {
@@ -180,9 +289,15 @@ impl CharacterKey {
             CharacterKey::Xianyun => "闲云",
             CharacterKey::Chiori => "千织",
             CharacterKey::Arlecchino => "阿蕾奇诺",
+            CharacterKey::Other(key) => names().character_name(self).unwrap_or(key),
         };
         String::from(temp)
     }
+
+    // the mona names are the in-game ones
+    pub fn from_mona(s: &str) -> Option<CharacterKey> {
+        names().character(s).cloned()
+    }
 }
 
 impl Serialize for ArtifactStat {
@@ -276,7 +391,7 @@ impl<'a> Serialize for MonaFormat<'a> {
 }
 
 impl<'a> MonaFormat<'a> {
-    pub fn new(results: &Vec<InternalArtifact>) -> MonaFormat {
+    pub fn new(results: &'a [InternalArtifact]) -> MonaFormat<'a> {
         let mut flower: Vec<&MonaArtifact> = Vec::new();
         let mut feather: Vec<&MonaArtifact> = Vec::new();
         let mut cup: Vec<&MonaArtifact> = Vec::new();
@@ -304,3 +419,200 @@ impl<'a> MonaFormat<'a> {
         }
     }
 }
+
+pub struct MonaExporter;
+
+impl Exporter for MonaExporter {
+    fn name(&self) -> &'static str {
+        "mona"
+    }
+
+    fn check(&self, artifact: &InternalArtifact) -> Result<Option<String>> {
+        Ok(match artifact.set_key.mona_key() {
+            Some(_) => None,
+            None => Some(format!(
+                "set {} has no mona key, written as is",
+                artifact.set_key
+            )),
+        })
+    }
+
+    fn serialize(&self, artifacts: &[InternalArtifact], _indices: &[usize]) -> Result<String> {
+        Ok(serde_json::to_string(&MonaFormat::new(artifacts))?)
+    }
+}
+
+// import
+
+#[derive(Deserialize)]
+struct MonaStatData {
+    name: String,
+    value: f64,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct MonaArtifactData {
+    set_name: String,
+    position: String,
+    main_tag: MonaStatData,
+    #[serde(default)]
+    normal_tags: Vec<MonaStatData>,
+    level: u32,
+    star: u32,
+    #[serde(default)]
+    equip: String,
+}
+
+#[derive(Deserialize)]
+struct MonaData {
+    #[serde(default)]
+    flower: Vec<MonaArtifactData>,
+    #[serde(default)]
+    feather: Vec<MonaArtifactData>,
+    #[serde(default)]
+    sand: Vec<MonaArtifactData>,
+    #[serde(default)]
+    cup: Vec<MonaArtifactData>,
+    #[serde(default)]
+    head: Vec<MonaArtifactData>,
+}
+
+fn stat_from_mona(stat: &MonaStatData) -> Result<ArtifactStat> {
+    let key = ArtifactStatKey::from_mona(&stat.name)
+        .ok_or_else(|| anyhow!("unknown stat `{}`", stat.name))?;
+    // percentages are stored divided by 100, round away the float error
+    let value = match key {
+        ArtifactStatKey::Atk
+        | ArtifactStatKey::ElementalMastery
+        | ArtifactStatKey::Hp
+        | ArtifactStatKey::Def => stat.value,
+        _ => (stat.value * 1000.0).round() / 10.0,
+    };
+    Ok(ArtifactStat { key, value })
+}
+
+fn artifact_from_mona(data: &MonaArtifactData) -> Result<InternalArtifact> {
+    let set_key = ArtifactSetKey::from_mona(&data.set_name)
+        .ok_or_else(|| anyhow!("unknown setName `{}`", data.set_name))?;
+    let slot_key = ArtifactSlotKey::from_mona(&data.position)
+        .ok_or_else(|| anyhow!("unknown position `{}`", data.position))?;
+    let location = match data.equip.as_str() {
+        "" => None,
+        s => Some(CharacterKey::from_mona(s).ok_or_else(|| anyhow!("unknown equip `{}`", s))?),
+    };
+
+    let mut substats = data.normal_tags.iter();
+    let mut next = || substats.next().map(stat_from_mona).transpose();
+
+    Ok(InternalArtifact {
+        set_key,
+        slot_key,
+        rarity: data.star,
+        level: data.level,
+        // not part of the format
+        lock: false,
+        location,
+        main_stat: stat_from_mona(&data.main_tag)?,
+        sub_stat_1: next()?,
+        sub_stat_2: next()?,
+        sub_stat_3: next()?,
+        sub_stat_4: next()?,
+    })
+}
+
+// Reads a mona.json. The order of the backpack is lost, artifacts come out
+// grouped by slot.
+pub fn import_mona(json_str: &str) -> Result<Vec<InternalArtifact>> {
+    let data: MonaData = serde_json::from_str(json_str)?;
+    [data.flower, data.feather, data.sand, data.cup, data.head]
+        .iter()
+        .flatten()
+        .enumerate()
+        .map(|(i, a)| artifact_from_mona(a).map_err(|e| anyhow!("artifact {}: {}", i, e)))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn artifact(
+        slot_key: ArtifactSlotKey,
+        main_stat: (ArtifactStatKey, f64),
+        sub_stats: &[(ArtifactStatKey, f64)],
+        location: Option<CharacterKey>,
+    ) -> InternalArtifact {
+        let stat = |(key, value): &(ArtifactStatKey, f64)| ArtifactStat {
+            key: key.clone(),
+            value: *value,
+        };
+        let mut subs = sub_stats.iter().map(stat);
+        InternalArtifact {
+            set_key: ArtifactSetKey::GladiatorsFinale,
+            slot_key,
+            rarity: 5,
+            level: 20,
+            lock: false,
+            location,
+            main_stat: stat(&main_stat),
+            sub_stat_1: subs.next(),
+            sub_stat_2: subs.next(),
+            sub_stat_3: subs.next(),
+            sub_stat_4: subs.next(),
+        }
+    }
+
+    // in the order import_mona groups them by slot
+    fn artifacts() -> Vec<InternalArtifact> {
+        use ArtifactStatKey::*;
+        vec![
+            artifact(
+                ArtifactSlotKey::Flower,
+                (Hp, 4780.0),
+                &[(CriticalDamage, 7.8), (Atk, 19.0), (Recharge, 5.8)],
+                Some(CharacterKey::Bennett),
+            ),
+            artifact(
+                ArtifactSlotKey::Sands,
+                (AtkPercentage, 46.6),
+                &[
+                    (Critical, 3.5),
+                    (ElementalMastery, 23.0),
+                    (DefPercentage, 7.3),
+                ],
+                None,
+            ),
+            artifact(
+                ArtifactSlotKey::Circlet,
+                (CriticalDamage, 62.2),
+                &[(HpPercentage, 4.1)],
+                None,
+            ),
+        ]
+    }
+
+    #[test]
+    fn round_trip() {
+        let artifacts = artifacts();
+        let json = MonaExporter.serialize(&artifacts, &[]).unwrap();
+        assert!(
+            json.contains(r#""name":"critical","value":0.035"#),
+            "{}",
+            json
+        );
+        assert_eq!(import_mona(&json).unwrap(), artifacts);
+    }
+
+    // percentages are stored as fractions, and read back with one decimal
+    #[test]
+    fn percentages_are_rounded_to_one_decimal() {
+        let json = r#"{"flower": [{"setName": "gladiatorFinale", "position": "flower",
+            "mainTag": {"name": "lifeStatic", "value": 4780}, "normalTags": [
+            {"name": "criticalDamage", "value": 0.07799999999},
+            {"name": "critical", "value": 0.0777}], "level": 20, "star": 5}]}"#;
+        let imported = import_mona(json).unwrap();
+        let values: Vec<f64> = imported[0].sub_stats().iter().map(|s| s.value).collect();
+        assert_eq!(values, vec![7.8, 7.8]);
+    }
+}
@@ -1,5 +1,11 @@
-use anyhow::Result;
+use anyhow::{anyhow, Result};
+use log::{info, warn};
+use serde::Serialize;
 use serde_json::Value;
+use std::borrow::Cow;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::Arc;
 use tract_onnx::prelude::*;
 
 use crate::common::RawImage;
@@ -7,33 +13,107 @@ use image::EncodableLayout;
 
 type ModelType = RunnableModel<TypedFact, Box<dyn TypedOp>, Graph<TypedFact, Box<dyn TypedOp>>>;
 
+#[derive(Debug, Clone, Serialize)]
+pub struct InferenceResult {
+    pub text: String,
+    // probability of every decoded character
+    pub char_confidences: Vec<f32>,
+    // the lowest of char_confidences, 1.0 for an empty string
+    pub confidence: f32,
+}
+
+impl InferenceResult {
+    // result of a blank image, which is not run through the model
+    pub fn empty() -> InferenceResult {
+        InferenceResult {
+            text: String::new(),
+            char_confidences: Vec::new(),
+            confidence: 1.0,
+        }
+    }
+}
+
+// Turns pre-processed 32x384 images into text. CRNNModel is the real one,
+// the simulator has its own so that scans can be tested without the model.
+pub trait TextModel: Send {
+    // every image must be pre-processed
+    fn inference_batch(&self, imgs: &[&RawImage]) -> Result<Vec<InferenceResult>>;
+
+    fn inference(&self, img: &RawImage) -> Result<InferenceResult> {
+        Ok(self.inference_batch(&[img])?.remove(0))
+    }
+
+    fn inference_string(&self, img: &RawImage) -> Result<String> {
+        Ok(self.inference(img)?.text)
+    }
+}
+
+// Creates a model for every thread that needs one.
+pub type ModelLoader = Arc<dyn Fn() -> Result<Box<dyn TextModel>> + Send + Sync>;
+
 pub struct CRNNModel {
     model: ModelType,
+    dynamic_batch: bool,
     index_2_word: Vec<String>,
 
     pub avg_inference_time: f64,
 }
 
+// Where to load the model from, the embedded model is used for anything
+// not given.
+#[derive(Clone, Debug, Default)]
+pub struct ModelPaths {
+    pub model: Option<PathBuf>,
+    pub vocab: Option<PathBuf>,
+}
+
 impl CRNNModel {
     pub fn new() -> Result<CRNNModel> {
+        Self::load(&ModelPaths::default())
+    }
+
+    pub fn loader(paths: ModelPaths) -> ModelLoader {
+        Arc::new(move || Ok(Box::new(CRNNModel::load(&paths)?) as Box<dyn TextModel>))
+    }
+
+    pub fn load(paths: &ModelPaths) -> Result<CRNNModel> {
         // let model = tract_onnx::onnx()
         //     .model_for_path(String::from("models/") + name.as_str()).unwrap()
         //     .with_input_fact(0, InferenceFact::dt_shape(f32::datum_type(), tvec!(1, 1, 32, 384))).unwrap()
         //     .into_optimized().unwrap()
         //     .into_runnable().unwrap();
-        let bytes = include_bytes!("../../models/model_training.onnx");
+        let bytes: Cow<[u8]> = match &paths.model {
+            Some(path) => {
+                info!("loading model from {}", path.display());
+                Cow::Owned(
+                    fs::read(path)
+                        .map_err(|e| anyhow!("cannot read model {}: {}", path.display(), e))?,
+                )
+            }
+            None => Cow::Borrowed(include_bytes!("../../models/model_training.onnx")),
+        };
 
-        let model = tract_onnx::onnx()
-            .model_for_read(&mut bytes.as_bytes())?
-            .with_input_fact(
-                0,
-                InferenceFact::dt_shape(f32::datum_type(), tvec!(1, 1, 32, 384)),
-            )?
-            .into_optimized()?
-            .into_runnable()?;
+        let batch = Symbol::new('N');
+        let (model, dynamic_batch) = match Self::load_model(&bytes, batch.to_dim()) {
+            Ok(model) => (model, true),
+            Err(e) => {
+                warn!(
+                    "model does not support dynamic batch, using batch size 1: {}",
+                    e
+                );
+                (Self::load_model(&bytes, 1.to_dim())?, false)
+            }
+        };
 
         // let content = utils::read_file_to_string(String::from("models/index_2_word.json"));
-        let content = String::from(include_str!("../../models/index_2_word.json"));
+        let content = match &paths.vocab {
+            Some(path) => {
+                info!("loading vocabulary from {}", path.display());
+                fs::read_to_string(path)
+                    .map_err(|e| anyhow!("cannot read vocabulary {}: {}", path.display(), e))?
+            }
+            None => String::from(include_str!("../../models/index_2_word.json")),
+        };
         let json: Value = serde_json::from_str(content.as_str())?;
 
         let mut index_2_word: Vec<String> = Vec::new();
@@ -47,34 +127,64 @@ impl CRNNModel {
             i += 1;
         }
 
-        Ok(CRNNModel {
+        let model = CRNNModel {
             model,
+            dynamic_batch,
             index_2_word,
 
             avg_inference_time: 0.0,
-        })
+        };
+        model.check_output_width()?;
+        Ok(model)
     }
 
-    pub fn inference_string(&self, img: &RawImage) -> Result<String> {
-        let tensor: Tensor =
-            tract_ndarray::Array4::from_shape_fn((1, 1, 32, 384), |(_, _, y, x)| {
-                let index = img.w * y as u32 + x as u32;
-                img.data[index as usize]
-            })
-            .into();
+    fn load_model(bytes: &[u8], batch: TDim) -> Result<ModelType> {
+        let model = tract_onnx::onnx()
+            .model_for_read(&mut bytes.as_bytes())?
+            .with_input_fact(
+                0,
+                InferenceFact::dt_shape(
+                    f32::datum_type(),
+                    tvec!(batch, 1.to_dim(), 32.to_dim(), 384.to_dim()),
+                ),
+            )?
+            .into_optimized()?
+            .into_runnable()?;
+        Ok(model)
+    }
 
+    // a mismatched model and vocabulary would decode garbage or panic on
+    // out of range indices, so run a blank image once to compare
+    fn check_output_width(&self) -> Result<()> {
+        let tensor: Tensor = tract_ndarray::Array4::<f32>::zeros((1, 1, 32, 384)).into();
         let result = self.model.run(tvec!(tensor))?;
-        let arr = result[0].to_array_view::<f32>()?;
+        let width = result[0].shape().last().cloned().unwrap_or(0);
+        if width != self.index_2_word.len() {
+            return Err(anyhow!(
+                "model outputs {} classes but the vocabulary has {} words",
+                width,
+                self.index_2_word.len()
+            ));
+        }
+        Ok(())
+    }
 
+    // greedy CTC decoding of one item of the output, shaped (T, N, classes)
+    fn decode(&self, arr: &tract_ndarray::ArrayViewD<f32>, b: usize) -> InferenceResult {
         let shape = arr.shape();
 
         let mut ans = String::new();
+        let mut char_confidences: Vec<f32> = Vec::new();
         let mut last_word = String::new();
         for i in 0..shape[0] {
+            let row: Vec<f32> = (0..self.index_2_word.len())
+                .map(|j| arr[[i, b, j]])
+                .collect();
+            let probs = to_probabilities(&row);
+
             let mut max_index = 0;
             let mut max_value = -1.0;
-            for j in 0..self.index_2_word.len() {
-                let value = arr[[i, 0, j]];
+            for (j, &value) in probs.iter().enumerate() {
                 if value > max_value {
                     max_value = value;
                     max_index = j;
@@ -83,11 +193,134 @@ impl CRNNModel {
             let word = &self.index_2_word[max_index];
             if *word != last_word && word != "-" {
                 ans = ans + word;
+                char_confidences.push(max_value);
+            } else if *word == last_word && word != "-" {
+                // CTC repeats of the same character, keep the best frame
+                if let Some(last) = char_confidences.last_mut() {
+                    *last = last.max(max_value);
+                }
             }
 
             last_word = word.clone();
         }
 
-        Ok(ans)
+        let confidence = char_confidences.iter().cloned().fold(1.0, f32::min);
+        InferenceResult {
+            text: ans,
+            char_confidences,
+            confidence,
+        }
+    }
+}
+
+impl TextModel for CRNNModel {
+    // Runs all images in a single pass when the model has a dynamic batch
+    // dimension, one by one otherwise. Every image must be pre-processed.
+    fn inference_batch(&self, imgs: &[&RawImage]) -> Result<Vec<InferenceResult>> {
+        if imgs.is_empty() {
+            return Ok(Vec::new());
+        }
+        if !self.dynamic_batch && imgs.len() > 1 {
+            let mut results = Vec::new();
+            for img in imgs {
+                results.append(&mut self.inference_batch(&[img])?);
+            }
+            return Ok(results);
+        }
+
+        let tensor: Tensor =
+            tract_ndarray::Array4::from_shape_fn((imgs.len(), 1, 32, 384), |(b, _, y, x)| {
+                let img = imgs[b];
+                let index = img.w * y as u32 + x as u32;
+                img.data[index as usize]
+            })
+            .into();
+
+        let result = self.model.run(tvec!(tensor))?;
+        let arr = result[0].to_array_view::<f32>()?;
+
+        Ok((0..imgs.len()).map(|b| self.decode(&arr, b)).collect())
+    }
+}
+
+// The exported model may end with a softmax or not, so normalize whatever it
+// outputs into probabilities.
+fn to_probabilities(row: &[f32]) -> Vec<f32> {
+    let sum: f32 = row.iter().sum();
+    if row.iter().all(|&v| v >= 0.0) && (sum - 1.0).abs() < 1e-3 {
+        return row.to_vec();
+    }
+
+    let max = row.iter().cloned().fold(f32::NEG_INFINITY, f32::max);
+    let exp: Vec<f32> = row.iter().map(|&v| (v - max).exp()).collect();
+    let sum: f32 = exp.iter().sum();
+    exp.into_iter().map(|v| v / sum).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Instant;
+
+    // pre-processed crops of four artifacts, nine fields each, with text of
+    // different lengths
+    fn crops() -> Vec<RawImage> {
+        (0..36_u32)
+            .map(|i| {
+                let width = 40 + i * 37 % 300;
+                let data = (0..32 * 384_u32)
+                    .map(|p| {
+                        let (x, y) = (p % 384, p / 384);
+                        let set = x < width && (4..28).contains(&y) && (x / 5 + y / 8 + i) % 3 != 0;
+                        if set {
+                            1.0
+                        } else {
+                            0.0
+                        }
+                    })
+                    .collect();
+                RawImage {
+                    data,
+                    w: 384,
+                    h: 32,
+                }
+            })
+            .collect()
+    }
+
+    // Throughput of the model on the same 36 crops, run one at a time, one
+    // artifact (9 crops) at a time and four artifacts (36 crops, BATCH_SIZE of
+    // the recognizer) at a time:
+    //   cargo test --release batch_throughput -- --ignored --nocapture
+    #[test]
+    #[ignore]
+    fn batch_throughput() {
+        const ROUNDS: usize = 5;
+        let model = CRNNModel::new().unwrap();
+        let crops = crops();
+        let imgs: Vec<&RawImage> = crops.iter().collect();
+        // warm up
+        model.inference_batch(&imgs).unwrap();
+
+        let mut texts = Vec::new();
+        for &batch in [1, 9, 36].iter() {
+            let start = Instant::now();
+            let mut results = Vec::new();
+            for _ in 0..ROUNDS {
+                results.clear();
+                for chunk in imgs.chunks(batch) {
+                    results.extend(model.inference_batch(chunk).unwrap());
+                }
+            }
+            let secs = start.elapsed().as_secs_f64();
+            println!(
+                "batch {:>2}: {:.1} images/s",
+                batch,
+                (ROUNDS * imgs.len()) as f64 / secs
+            );
+            texts.push(results.into_iter().map(|r| r.text).collect::<Vec<_>>());
+        }
+        // batching must not change what is read
+        assert!(texts.windows(2).all(|w| w[0] == w[1]));
     }
 }
@@ -0,0 +1,184 @@
+use std::sync::{Arc, Mutex};
+use std::time::SystemTime;
+
+#[cfg(windows)]
+use enigo::{Enigo, MouseButton, MouseControllable};
+use log::trace;
+use serde::Serialize;
+
+// Mouse operations YasScanner needs. Buttons are always the left one.
+pub trait InputDriver {
+    fn move_to(&mut self, x: i32, y: i32);
+    fn click(&mut self);
+    fn scroll(&mut self, length: i32);
+    fn mouse_down(&mut self);
+    fn mouse_up(&mut self);
+}
+
+#[cfg(windows)]
+pub struct EnigoDriver {
+    enigo: Enigo,
+}
+
+#[cfg(windows)]
+impl EnigoDriver {
+    pub fn new() -> EnigoDriver {
+        EnigoDriver {
+            enigo: Enigo::new(),
+        }
+    }
+}
+
+#[cfg(windows)]
+impl InputDriver for EnigoDriver {
+    fn move_to(&mut self, x: i32, y: i32) {
+        self.enigo.mouse_move_to(x, y);
+    }
+
+    fn click(&mut self) {
+        self.enigo.mouse_click(MouseButton::Left);
+    }
+
+    fn scroll(&mut self, length: i32) {
+        self.enigo.mouse_scroll_y(length);
+    }
+
+    fn mouse_down(&mut self) {
+        self.enigo.mouse_down(MouseButton::Left);
+    }
+
+    fn mouse_up(&mut self) {
+        self.enigo.mouse_up(MouseButton::Left);
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub enum InputAction {
+    MoveTo(i32, i32),
+    Click,
+    Scroll(i32),
+    MouseDown,
+    MouseUp,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct InputEvent {
+    // ms since the driver was created
+    pub time: u128,
+    pub action: InputAction,
+}
+
+// Shared view of the events of a RecordingDriver, still readable after the
+// driver has been moved into a scanner.
+#[derive(Clone, Default)]
+pub struct InputLog(Arc<Mutex<Vec<InputEvent>>>);
+
+impl InputLog {
+    pub fn events(&self) -> Vec<InputEvent> {
+        self.0.lock().unwrap().clone()
+    }
+
+    // cursor position of every click, in order
+    pub fn clicks(&self) -> Vec<(i32, i32)> {
+        let mut pos = (0, 0);
+        let mut clicks = Vec::new();
+        for e in self.0.lock().unwrap().iter() {
+            match e.action {
+                InputAction::MoveTo(x, y) => pos = (x, y),
+                InputAction::Click => clicks.push(pos),
+                _ => {}
+            }
+        }
+        clicks
+    }
+
+    pub fn clear(&self) {
+        self.0.lock().unwrap().clear();
+    }
+
+    fn push(&self, event: InputEvent) {
+        self.0.lock().unwrap().push(event);
+    }
+}
+
+// Logs every operation instead of touching the real mouse, or before passing
+// it on to another driver.
+pub struct RecordingDriver {
+    start: SystemTime,
+    log: InputLog,
+    inner: Option<Box<dyn InputDriver>>,
+}
+
+impl Default for RecordingDriver {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl RecordingDriver {
+    pub fn new() -> RecordingDriver {
+        RecordingDriver {
+            start: SystemTime::now(),
+            log: InputLog::default(),
+            inner: None,
+        }
+    }
+
+    pub fn wrap(inner: Box<dyn InputDriver>) -> RecordingDriver {
+        RecordingDriver {
+            inner: Some(inner),
+            ..Self::new()
+        }
+    }
+
+    pub fn log(&self) -> InputLog {
+        self.log.clone()
+    }
+
+    fn record(&mut self, action: InputAction) {
+        let time = self.start.elapsed().map_or(0, |t| t.as_millis());
+        trace!("input: {:?} at {}ms", action, time);
+        self.log.push(InputEvent { time, action });
+    }
+
+    fn inner(&mut self) -> Option<&mut Box<dyn InputDriver>> {
+        self.inner.as_mut()
+    }
+}
+
+impl InputDriver for RecordingDriver {
+    fn move_to(&mut self, x: i32, y: i32) {
+        self.record(InputAction::MoveTo(x, y));
+        if let Some(inner) = self.inner() {
+            inner.move_to(x, y);
+        }
+    }
+
+    fn click(&mut self) {
+        self.record(InputAction::Click);
+        if let Some(inner) = self.inner() {
+            inner.click();
+        }
+    }
+
+    fn scroll(&mut self, length: i32) {
+        self.record(InputAction::Scroll(length));
+        if let Some(inner) = self.inner() {
+            inner.scroll(length);
+        }
+    }
+
+    fn mouse_down(&mut self) {
+        self.record(InputAction::MouseDown);
+        if let Some(inner) = self.inner() {
+            inner.mouse_down();
+        }
+    }
+
+    fn mouse_up(&mut self) {
+        self.record(InputAction::MouseUp);
+        if let Some(inner) = self.inner() {
+            inner.mouse_up();
+        }
+    }
+}
@@ -4,6 +4,9 @@ pub mod common;
 pub mod expo;
 pub mod inference;
 pub mod info;
+pub mod input;
 pub mod lock;
+pub mod platform;
 pub mod scanner;
+pub mod simulator;
 pub mod ws;
@@ -0,0 +1,360 @@
+use anyhow::{anyhow, Result};
+use std::cmp::min;
+use std::fs;
+use std::path::Path;
+
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+
+use crate::artifact::internal_artifact::InternalArtifact;
+use crate::common::PixelRect;
+use crate::expo::good;
+use crate::info::info::ScanInfo;
+use crate::lock::{LockAction, LockActionType};
+use crate::scanner::artifact_recognizer::ScanSummary;
+
+// Runs lock actions against a saved scan instead of the game, to see what
+// YasScanner::lock would do. The backpack is the scan in backpack order, with
+// None for slots that were not recognized or not exported. Slots after the
+// last one in the exports only count through the scanned art_count.
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DryRunIssue {
+    pub index: u32,
+    pub message: String,
+}
+
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct DryRunReport {
+    pub artifacts: usize,
+    pub lock: Vec<u32>,
+    pub unlock: Vec<u32>,
+    pub flip: Vec<u32>,
+    // lock and unlock targets that are already in that state
+    pub unchanged: Vec<u32>,
+    // the real run stops at the first one
+    pub failed_validations: Vec<DryRunIssue>,
+    pub fingerprint_mismatches: Vec<DryRunIssue>,
+    // targets that were not recognized or were left out by --min-level,
+    // their lock state is unknown
+    pub unknown: Vec<u32>,
+    pub out_of_range: Vec<u32>,
+    pub pages_scrolled: u32,
+}
+
+impl DryRunReport {
+    pub fn ok(&self) -> bool {
+        self.failed_validations.is_empty()
+            && self.fingerprint_mismatches.is_empty()
+            && self.unknown.is_empty()
+            && self.out_of_range.is_empty()
+    }
+
+    pub fn log(&self) {
+        info!(
+            "模拟加解锁: 加锁{}个，解锁{}个，切换{}个，无需操作{}个，翻页{}次",
+            self.lock.len(),
+            self.unlock.len(),
+            self.flip.len(),
+            self.unchanged.len(),
+            self.pages_scrolled
+        );
+        for issue in self.failed_validations.iter() {
+            warn!("第{}个圣遗物验证失败: {}", issue.index, issue.message);
+        }
+        for issue in self.fingerprint_mismatches.iter() {
+            warn!(
+                "第{}个圣遗物与lock.json不符: {}",
+                issue.index, issue.message
+            );
+        }
+        if !self.unknown.is_empty() {
+            warn!(
+                "未识别或未导出的圣遗物，无法判断加解锁状态: {:?}",
+                self.unknown
+            );
+        }
+        if !self.out_of_range.is_empty() {
+            warn!("超出圣遗物数量: {:?}", self.out_of_range);
+        }
+    }
+}
+
+// the artifacts of good.json and the indices of unrecognized.json of a scan.
+// Slots in neither were left out by --min-level. good.json written before it
+// had `index` lists the recognized artifacts in backpack order, the
+// unrecognized slots fill the gaps, which is wrong for a --min-level scan.
+pub fn backpack(
+    artifacts: Vec<(Option<usize>, InternalArtifact)>,
+    unrecognized: &[u32],
+) -> Result<Vec<Option<InternalArtifact>>> {
+    let n_indexed = artifacts.iter().filter(|(i, _)| i.is_some()).count();
+    if n_indexed == 0 {
+        if !artifacts.is_empty() {
+            warn!("good.json没有index（扫描时加--good-extra才会输出），按顺序对应背包位置，扫描时用了--min-level则会错位");
+        }
+        let total = artifacts.len() + unrecognized.len();
+        let mut artifacts = artifacts.into_iter().map(|(_, a)| a);
+        return Ok((0..total as u32)
+            .map(|i| {
+                if unrecognized.contains(&i) {
+                    None
+                } else {
+                    artifacts.next()
+                }
+            })
+            .collect());
+    }
+    if n_indexed != artifacts.len() {
+        return Err(anyhow!("some artifacts in good.json have no index"));
+    }
+
+    let total = artifacts
+        .iter()
+        .filter_map(|(i, _)| *i)
+        .chain(unrecognized.iter().map(|&i| i as usize))
+        .max()
+        .map_or(0, |i| i + 1);
+    let mut slots: Vec<Option<InternalArtifact>> = vec![None; total];
+    for (index, artifact) in artifacts {
+        let index = index.unwrap();
+        if slots[index].is_some() || unrecognized.contains(&(index as u32)) {
+            return Err(anyhow!("duplicate index {} in scan", index));
+        }
+        slots[index] = Some(artifact);
+    }
+    Ok(slots)
+}
+
+#[derive(Deserialize)]
+struct UnrecognizedIndex {
+    index: u32,
+}
+
+// the art_count of a scan, at least the size of its backpack
+pub fn art_count(backpack: &[Option<InternalArtifact>], art_count: Option<u32>) -> Result<u32> {
+    match art_count {
+        Some(n) if (n as usize) < backpack.len() => Err(anyhow!(
+            "art_count {} is less than the {} slots in the scan",
+            n,
+            backpack.len()
+        )),
+        Some(n) => Ok(n),
+        None => {
+            warn!("没有扫描的圣遗物总数，扫描时用了--min-level则最后几个圣遗物会算作超出数量");
+            Ok(backpack.len() as u32)
+        }
+    }
+}
+
+// good.json and the unrecognized.json and scan_summary.json next to it, if
+// any, as the backpack and its art_count
+pub fn load_scan(good_path: &Path) -> Result<(Vec<Option<InternalArtifact>>, u32)> {
+    let artifacts = good::import_good_file(good_path)?;
+    let unrecognized_path = good_path.with_file_name("unrecognized.json");
+    let unrecognized: Vec<u32> = if unrecognized_path.exists() {
+        let json_str = fs::read_to_string(&unrecognized_path)?;
+        serde_json::from_str::<Vec<UnrecognizedIndex>>(&json_str)?
+            .into_iter()
+            .map(|r| r.index)
+            .collect()
+    } else {
+        Vec::new()
+    };
+    let summary_path = good_path.with_file_name("scan_summary.json");
+    let summary: Option<ScanSummary> = if summary_path.exists() {
+        Some(serde_json::from_str(&fs::read_to_string(&summary_path)?)?)
+    } else {
+        None
+    };
+
+    let backpack = backpack(artifacts, &unrecognized)?;
+    let art_count = art_count(&backpack, summary.map(|s| s.art_count))?;
+    Ok((backpack, art_count))
+}
+
+// rows and columns of the backpack grid for a window size like `1920x1080`
+pub fn grid_of_window(size: &str) -> Result<(u32, u32)> {
+    let parse = || -> Option<(i32, i32)> {
+        let (w, h) = size.split_once('x')?;
+        Some((w.trim().parse().ok()?, h.trim().parse().ok()?))
+    };
+    let (width, height) = parse().ok_or_else(|| anyhow!("invalid window size `{}`", size))?;
+    let info = ScanInfo::from_rect(&PixelRect {
+        left: 0,
+        top: 0,
+        width,
+        height,
+    })
+    .map_err(|e| anyhow!(e))?;
+    Ok((info.art_row, info.art_col))
+}
+
+// same paging as YasScanner::lock
+fn count_scrolls(actions: &[LockAction], total: u32, rows: u32, cols: u32) -> u32 {
+    let total_rows = match total % cols {
+        0 => total / cols,
+        _ => total / cols + 1,
+    };
+    let mut scrolled_rows = 0;
+    let mut end_action = 0;
+    let mut scrolls = 0;
+    while end_action < actions.len() {
+        let end_art = min(cols * (scrolled_rows + rows), total);
+        while end_action < actions.len() && actions[end_action].target < end_art {
+            end_action += 1;
+        }
+        if total_rows <= scrolled_rows + rows || end_action >= actions.len() {
+            break;
+        }
+        scrolled_rows += min(total_rows - scrolled_rows - rows, rows);
+        scrolls += 1;
+    }
+    scrolls
+}
+
+pub fn simulate(
+    actions: &[LockAction],
+    backpack: &[Option<InternalArtifact>],
+    art_count: u32,
+    rows: u32,
+    cols: u32,
+) -> DryRunReport {
+    let mut report = DryRunReport {
+        artifacts: art_count as usize,
+        pages_scrolled: count_scrolls(actions, art_count, rows, cols),
+        ..Default::default()
+    };
+
+    // a target gets one click at most (see LockAction::validate) and the
+    // validations of a page run before its clicks, so every action sees the
+    // scanned lock state
+    for a in actions.iter() {
+        if a.target >= art_count {
+            report.out_of_range.push(a.target);
+            continue;
+        }
+        // past the end of the backpack are slots left out by --min-level
+        let artifact = match backpack.get(a.target as usize) {
+            Some(Some(artifact)) => artifact,
+            _ => {
+                report.unknown.push(a.target);
+                continue;
+            }
+        };
+        let locked = artifact.lock;
+        if let Some(reason) = a.fingerprint.as_ref().and_then(|f| f.mismatch(artifact)) {
+            report.fingerprint_mismatches.push(DryRunIssue {
+                index: a.target,
+                message: reason,
+            });
+            continue;
+        }
+
+        match a.type_ {
+            LockActionType::ValidateLocked | LockActionType::ValidateUnlocked => {
+                let should_be_locked = a.type_ == LockActionType::ValidateLocked;
+                if locked != should_be_locked {
+                    let expected = if should_be_locked {
+                        "locked"
+                    } else {
+                        "unlocked"
+                    };
+                    report.failed_validations.push(DryRunIssue {
+                        index: a.target,
+                        message: format!("should be {}", expected),
+                    });
+                }
+            }
+            LockActionType::Lock if !locked => report.lock.push(a.target),
+            LockActionType::Unlock if locked => report.unlock.push(a.target),
+            LockActionType::Lock | LockActionType::Unlock => report.unchanged.push(a.target),
+            LockActionType::Flip => report.flip.push(a.target),
+        }
+    }
+    report
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::artifact::internal_artifact::{
+        ArtifactSetKey, ArtifactSlotKey, ArtifactStat, ArtifactStatKey,
+    };
+
+    fn artifact(lock: bool) -> InternalArtifact {
+        InternalArtifact {
+            set_key: ArtifactSetKey::GladiatorsFinale,
+            slot_key: ArtifactSlotKey::Flower,
+            rarity: 5,
+            level: 20,
+            lock,
+            location: None,
+            main_stat: ArtifactStat {
+                key: ArtifactStatKey::Hp,
+                value: 4780.0,
+            },
+            sub_stat_1: None,
+            sub_stat_2: None,
+            sub_stat_3: None,
+            sub_stat_4: None,
+        }
+    }
+
+    #[test]
+    fn backpack_by_index() {
+        // slot 1 unrecognized, slot 2 left out by --min-level
+        let artifacts = vec![(Some(0), artifact(false)), (Some(3), artifact(true))];
+        let slots = backpack(artifacts, &[1]).unwrap();
+        let locks: Vec<Option<bool>> = slots.iter().map(|a| a.as_ref().map(|a| a.lock)).collect();
+        assert_eq!(locks, vec![Some(false), None, None, Some(true)]);
+
+        let artifacts = vec![(Some(0), artifact(false)), (None, artifact(true))];
+        assert!(backpack(artifacts, &[]).is_err());
+    }
+
+    #[test]
+    fn backpack_without_index() {
+        let artifacts = vec![(None, artifact(false)), (None, artifact(true))];
+        let slots = backpack(artifacts, &[0]).unwrap();
+        let locks: Vec<Option<bool>> = slots.iter().map(|a| a.as_ref().map(|a| a.lock)).collect();
+        assert_eq!(locks, vec![None, Some(false), Some(true)]);
+    }
+
+    #[test]
+    fn scrolls() {
+        let flip = |target| LockAction {
+            target,
+            type_: LockActionType::Flip,
+            fingerprint: None,
+        };
+        // 5 rows of 8, 100 artifacts are 13 rows
+        assert_eq!(count_scrolls(&[flip(3)], 100, 5, 8), 0);
+        assert_eq!(count_scrolls(&[flip(3), flip(40)], 100, 5, 8), 1);
+        assert_eq!(count_scrolls(&[flip(99)], 100, 5, 8), 2);
+    }
+
+    #[test]
+    fn trailing_skipped_slots_are_unknown() {
+        let flip = |target| LockAction {
+            target,
+            type_: LockActionType::Flip,
+            fingerprint: None,
+        };
+        // slots 2 and 3 left out by --min-level, the backpack ends at 1
+        let slots = backpack(
+            vec![(Some(0), artifact(false)), (Some(1), artifact(true))],
+            &[],
+        )
+        .unwrap();
+        let actions = [flip(1), flip(3), flip(4)];
+        let report = simulate(&actions, &slots, art_count(&slots, Some(4)).unwrap(), 5, 8);
+        assert_eq!(report.artifacts, 4);
+        assert_eq!(report.flip, vec![1]);
+        assert_eq!(report.unknown, vec![3]);
+        assert_eq!(report.out_of_range, vec![4]);
+
+        assert!(art_count(&slots, Some(1)).is_err());
+        assert_eq!(art_count(&slots, None).unwrap(), 2);
+    }
+}
@@ -0,0 +1,113 @@
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+
+use crate::artifact::internal_artifact::{
+    ArtifactSetKey, ArtifactSlotKey, ArtifactStatKey, InternalArtifact,
+};
+
+// What a lock.json v3 entry expects to find at its index, with GOOD keys.
+// The lock state and location are left out since they are what may change
+// between the scan and the lock run.
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FingerprintStat {
+    pub key: String,
+    pub value: f64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ArtifactFingerprint {
+    pub set_key: String,
+    pub slot_key: String,
+    pub rarity: u32,
+    pub level: u32,
+    pub main_stat_key: String,
+    pub substats: Vec<FingerprintStat>,
+}
+
+// OCR'd values are exact up to the displayed precision
+const VALUE_TOLERANCE: f64 = 0.05;
+
+impl ArtifactFingerprint {
+    pub fn from_artifact(artifact: &InternalArtifact) -> ArtifactFingerprint {
+        ArtifactFingerprint {
+            set_key: artifact.set_key.to_good(),
+            slot_key: artifact.slot_key.to_good(),
+            rarity: artifact.rarity,
+            level: artifact.level,
+            main_stat_key: artifact.main_stat.key.to_good(),
+            substats: artifact
+                .sub_stats()
+                .into_iter()
+                .map(|s| FingerprintStat {
+                    key: s.key.to_good(),
+                    value: s.value,
+                })
+                .collect(),
+        }
+    }
+
+    // rejects keys that no artifact could have, so that a typo in lock.json
+    // fails before anything is clicked
+    pub fn check(&self) -> Result<()> {
+        ArtifactSetKey::from_good(&self.set_key)
+            .ok_or_else(|| anyhow!("unknown set key `{}`", self.set_key))?;
+        ArtifactSlotKey::from_good(&self.slot_key)
+            .ok_or_else(|| anyhow!("unknown slot key `{}`", self.slot_key))?;
+        for key in std::iter::once(&self.main_stat_key).chain(self.substats.iter().map(|s| &s.key))
+        {
+            ArtifactStatKey::from_good(key).ok_or_else(|| anyhow!("unknown stat key `{}`", key))?;
+        }
+        if self.substats.len() > 4 {
+            return Err(anyhow!("more than 4 substats"));
+        }
+        Ok(())
+    }
+
+    // the first field that differs, None if the artifact matches
+    pub fn mismatch(&self, artifact: &InternalArtifact) -> Option<String> {
+        self.diff(&Self::from_artifact(artifact))
+    }
+
+    // like mismatch, for a fingerprint read from the screen
+    pub fn diff(&self, found: &ArtifactFingerprint) -> Option<String> {
+        let field = |name: &str, expected: &dyn ToString, found: &dyn ToString| {
+            Some(format!(
+                "{}: expected {}, found {}",
+                name,
+                expected.to_string(),
+                found.to_string()
+            ))
+        };
+
+        if self.set_key != found.set_key {
+            return field("set", &self.set_key, &found.set_key);
+        }
+        if self.slot_key != found.slot_key {
+            return field("slot", &self.slot_key, &found.slot_key);
+        }
+        if self.rarity != found.rarity {
+            return field("rarity", &self.rarity, &found.rarity);
+        }
+        if self.level != found.level {
+            return field("level", &self.level, &found.level);
+        }
+        if self.main_stat_key != found.main_stat_key {
+            return field("main stat", &self.main_stat_key, &found.main_stat_key);
+        }
+        if self.substats.len() != found.substats.len() {
+            return field("substat count", &self.substats.len(), &found.substats.len());
+        }
+        for (expected, found) in self.substats.iter().zip(found.substats.iter()) {
+            if expected.key != found.key || (expected.value - found.value).abs() > VALUE_TOLERANCE {
+                return field(
+                    "substat",
+                    &format!("{}={}", expected.key, expected.value),
+                    &format!("{}={}", found.key, found.value),
+                );
+            }
+        }
+        None
+    }
+}
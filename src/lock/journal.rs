@@ -0,0 +1,177 @@
+use anyhow::{anyhow, Result};
+use std::collections::HashMap;
+use std::fs::{self, File, OpenOptions};
+use std::io::Write;
+use std::path::Path;
+
+use log::warn;
+use serde::{Deserialize, Serialize};
+
+use crate::lock::{LockAction, LockActionType};
+
+// Progress of a lock run, one json line per target whose lock has been
+// clicked, written right after the click. A run stopped midway can be resumed
+// with the same lock.json: the targets in the journal are locked or unlocked
+// to the state they were clicked to, which only clicks if the state is wrong.
+// So a v1 flip list does not toggle them back, and a click that did not take
+// before the run stopped is redone. The journal of a run that succeeded is
+// removed, so one that is left over belongs to a failed or stopped run.
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JournalEntry {
+    pub target: u32,
+    // lock state the target was set to
+    pub locked: bool,
+}
+
+pub struct LockJournal {
+    file: File,
+}
+
+impl LockJournal {
+    // starts an empty journal, the one of an unfinished run is kept as .bak
+    // unless there already is one
+    pub fn create(path: &Path) -> Result<LockJournal> {
+        if matches!(fs::metadata(path), Ok(m) if m.len() > 0) {
+            let backup = path.with_extension("jsonl.bak");
+            if backup.exists() {
+                return Err(anyhow!(
+                    "{}有上次加解锁的记录，{}也已存在，请先移走其中一个，或加--resume继续上次的加解锁",
+                    path.display(),
+                    backup.display()
+                ));
+            }
+            warn!(
+                "{}有上次加解锁的记录，已移到{}，继续上次的加解锁请加--resume",
+                path.display(),
+                backup.display()
+            );
+            fs::rename(path, &backup)
+                .map_err(|e| anyhow!("cannot move {}: {}", path.display(), e))?;
+        }
+        let file =
+            File::create(path).map_err(|e| anyhow!("cannot create {}: {}", path.display(), e))?;
+        Ok(LockJournal { file })
+    }
+
+    // continues the journal of a resumed run
+    pub fn append(path: &Path) -> Result<LockJournal> {
+        let file = OpenOptions::new()
+            .append(true)
+            .open(path)
+            .map_err(|e| anyhow!("cannot open {}: {}", path.display(), e))?;
+        Ok(LockJournal { file })
+    }
+
+    // removes the journal of a run that succeeded
+    pub fn finish(path: &Path) -> Result<()> {
+        fs::remove_file(path).map_err(|e| anyhow!("cannot remove {}: {}", path.display(), e))
+    }
+
+    pub fn record(&mut self, target: u32, locked: bool) -> Result<()> {
+        let line = serde_json::to_string(&JournalEntry { target, locked })?;
+        writeln!(self.file, "{}", line)?;
+        Ok(())
+    }
+
+    // the last line may be cut off if the program was killed while writing it
+    pub fn load(path: &Path) -> Result<Vec<JournalEntry>> {
+        let content = fs::read_to_string(path)
+            .map_err(|e| anyhow!("cannot read {}: {}", path.display(), e))?;
+        let lines: Vec<&str> = content.lines().filter(|l| !l.trim().is_empty()).collect();
+        let mut entries = Vec::new();
+        for (i, line) in lines.iter().enumerate() {
+            match serde_json::from_str(line) {
+                Ok(entry) => entries.push(entry),
+                Err(e) if i + 1 == lines.len() => warn!("ignored broken journal line: {}", e),
+                Err(e) => return Err(anyhow!("{} line {}: {}", path.display(), i + 1, e)),
+            }
+        }
+        Ok(entries)
+    }
+}
+
+impl LockAction {
+    // Replaces the actions of targets in the journal with a lock or unlock to
+    // the state they were clicked to. Journal entries of targets that are not
+    // in `actions` are ignored.
+    pub fn resume(actions: Vec<LockAction>, done: &[JournalEntry]) -> Result<Vec<LockAction>> {
+        let done: HashMap<u32, bool> = done.iter().map(|e| (e.target, e.locked)).collect();
+
+        let mut resumed: Vec<LockAction> = Vec::new();
+        let mut redone: HashMap<u32, LockAction> = HashMap::new();
+        for a in actions.into_iter() {
+            match done.get(&a.target) {
+                Some(&locked) => {
+                    let v = redone.entry(a.target).or_insert(LockAction {
+                        target: a.target,
+                        type_: if locked {
+                            LockActionType::Lock
+                        } else {
+                            LockActionType::Unlock
+                        },
+                        fingerprint: None,
+                    });
+                    if v.fingerprint.is_none() {
+                        v.fingerprint = a.fingerprint;
+                    }
+                }
+                None => resumed.push(a),
+            }
+        }
+        resumed.extend(redone.into_values());
+        resumed.sort_by_key(|x| x.target);
+
+        Self::validate(&resumed)?;
+        Ok(resumed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn resume_sets_journaled_targets() {
+        let json = r#"{"version": 2, "flip_indices": [1, 3], "lock_indices": [],
+            "unlock_indices": [], "validation": [{"index": 1, "locked": false}]}"#;
+        let actions = LockAction::from_lock_json(json).unwrap();
+        let done = vec![JournalEntry {
+            target: 1,
+            locked: true,
+        }];
+        let types: Vec<(u32, LockActionType)> = LockAction::resume(actions, &done)
+            .unwrap()
+            .into_iter()
+            .map(|a| (a.target, a.type_))
+            .collect();
+        assert_eq!(
+            types,
+            vec![(1, LockActionType::Lock), (3, LockActionType::Flip)]
+        );
+    }
+
+    #[test]
+    fn create_keeps_unfinished_journal() {
+        let dir = std::env::temp_dir().join(format!("yas_journal_{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("lock_journal.jsonl");
+        LockJournal::create(&path).unwrap().record(4, true).unwrap();
+
+        LockJournal::create(&path).unwrap();
+        assert_eq!(LockJournal::load(&path).unwrap().len(), 0);
+        let backup = LockJournal::load(&path.with_extension("jsonl.bak")).unwrap();
+        assert_eq!(backup[0].target, 4);
+
+        // the .bak is never overwritten
+        LockJournal::create(&path).unwrap().record(5, true).unwrap();
+        assert!(LockJournal::create(&path).is_err());
+        let backup = LockJournal::load(&path.with_extension("jsonl.bak")).unwrap();
+        assert_eq!(backup[0].target, 4);
+        assert_eq!(LockJournal::load(&path).unwrap()[0].target, 5);
+
+        LockJournal::finish(&path).unwrap();
+        assert!(!path.exists());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
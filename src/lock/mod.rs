@@ -1,7 +1,15 @@
 use anyhow::{anyhow, Result};
+use log::{info, warn};
 use regex::Regex;
 use serde::{Deserialize, Serialize};
 
+use crate::lock::fingerprint::ArtifactFingerprint;
+
+pub mod dry_run;
+pub mod fingerprint;
+pub mod journal;
+pub mod rules;
+
 // lock.json format v1
 // array of indices (to flip)
 
@@ -22,9 +30,74 @@ pub struct LockFormatV2 {
     validation: Vec<LockValidationRecord>,
 }
 
+// lock.json format v3
+// like v2, but every entry carries the artifact expected at the index, which
+// is checked by OCR before anything is clicked
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct LockTargetV3 {
+    index: u32,
+    artifact: ArtifactFingerprint,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct LockValidationRecordV3 {
+    index: u32,
+    locked: bool,
+    artifact: ArtifactFingerprint,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct LockFormatV3 {
+    version: u32,
+    flip: Vec<LockTargetV3>,
+    lock: Vec<LockTargetV3>,
+    unlock: Vec<LockTargetV3>,
+    validation: Vec<LockValidationRecordV3>,
+}
+
+// result of YasScanner::lock, by lock.json index
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct LockReport {
+    // confirmed on the first try
+    pub succeeded: Vec<u32>,
+    // confirmed after clicking again
+    pub retried: Vec<u32>,
+    // still wrong after all retries
+    pub failed: Vec<u32>,
+    // v3 targets that did not match their fingerprint, not flipped
+    pub mismatched: Vec<u32>,
+    // flipped but not checked, the run was stopped with the right button
+    pub unverified: Vec<u32>,
+}
+
+impl LockReport {
+    pub fn ok(&self) -> bool {
+        self.failed.is_empty() && self.mismatched.is_empty() && self.unverified.is_empty()
+    }
+
+    pub fn log(&self) {
+        info!(
+            "加解锁完成: 成功{}个，重试后成功{}个，失败{}个，不符{}个，未确认{}个",
+            self.succeeded.len(),
+            self.retried.len(),
+            self.failed.len(),
+            self.mismatched.len(),
+            self.unverified.len()
+        );
+        if !self.ok() {
+            warn!(
+                "失败: {:?}，不符: {:?}，未确认: {:?}",
+                self.failed, self.mismatched, self.unverified
+            );
+        }
+    }
+}
+
 // internal format
 
-#[derive(PartialEq, Debug)]
+#[derive(PartialEq, Debug, Clone)]
 pub enum LockActionType {
     ValidateLocked,
     ValidateUnlocked,
@@ -37,6 +110,8 @@ pub enum LockActionType {
 pub struct LockAction {
     pub target: u32,
     pub type_: LockActionType,
+    // v3 only, the target is skipped if it does not match
+    pub fingerprint: Option<ArtifactFingerprint>,
 }
 
 impl LockAction {
@@ -46,6 +121,7 @@ impl LockAction {
             .map(|x| LockAction {
                 target: *x,
                 type_: LockActionType::Flip,
+                fingerprint: None,
             })
             .collect();
 
@@ -61,6 +137,7 @@ impl LockAction {
             actions.push(LockAction {
                 target: *x,
                 type_: LockActionType::Flip,
+                fingerprint: None,
             })
         });
 
@@ -68,6 +145,7 @@ impl LockAction {
             actions.push(LockAction {
                 target: *x,
                 type_: LockActionType::Lock,
+                fingerprint: None,
             })
         });
 
@@ -75,6 +153,7 @@ impl LockAction {
             actions.push(LockAction {
                 target: *x,
                 type_: LockActionType::Unlock,
+                fingerprint: None,
             })
         });
 
@@ -86,6 +165,7 @@ impl LockAction {
                 } else {
                     LockActionType::ValidateUnlocked
                 },
+                fingerprint: None,
             })
         });
 
@@ -94,6 +174,47 @@ impl LockAction {
         return actions;
     }
 
+    pub fn from_v3(data: &LockFormatV3) -> Result<Vec<LockAction>> {
+        let mut actions: Vec<LockAction> = Vec::new();
+
+        let targets = [
+            (&data.flip, LockActionType::Flip),
+            (&data.lock, LockActionType::Lock),
+            (&data.unlock, LockActionType::Unlock),
+        ];
+        for (list, type_) in targets.iter() {
+            for x in list.iter() {
+                x.artifact
+                    .check()
+                    .map_err(|e| anyhow!("lock.json index {}: {}", x.index, e))?;
+                actions.push(LockAction {
+                    target: x.index,
+                    type_: type_.clone(),
+                    fingerprint: Some(x.artifact.clone()),
+                });
+            }
+        }
+
+        for x in data.validation.iter() {
+            x.artifact
+                .check()
+                .map_err(|e| anyhow!("lock.json index {}: {}", x.index, e))?;
+            actions.push(LockAction {
+                target: x.index,
+                type_: if x.locked {
+                    LockActionType::ValidateLocked
+                } else {
+                    LockActionType::ValidateUnlocked
+                },
+                fingerprint: Some(x.artifact.clone()),
+            });
+        }
+
+        actions.sort_by_key(|x| x.target);
+
+        Ok(actions)
+    }
+
     pub fn validate(actions: &Vec<LockAction>) -> Result<()> {
         let mut a_target = u32::MAX;
         let mut n_validation = 0;
@@ -143,6 +264,15 @@ impl LockAction {
             return Ok(actions);
         }
 
+        // v3
+        let re_v3 = Regex::new(r#""version"\s*:\s*3"#).unwrap();
+        if re_v3.is_match(json_str) {
+            let data: LockFormatV3 = serde_json::from_str(json_str)?;
+            let actions = Self::from_v3(&data)?;
+            Self::validate(&actions)?;
+            return Ok(actions);
+        }
+
         Err(anyhow!("Unknown lock.json version"))
     }
 }
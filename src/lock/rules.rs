@@ -0,0 +1,221 @@
+use anyhow::{anyhow, Result};
+use std::fs;
+use std::path::Path;
+
+use log::info;
+use serde::Deserialize;
+
+use crate::artifact::internal_artifact::{
+    ArtifactSetKey, ArtifactSlotKey, ArtifactStatKey, CharacterKey, InternalArtifact,
+};
+use crate::lock::fingerprint::ArtifactFingerprint;
+use crate::lock::{LockFormatV3, LockTargetV3, LockValidationRecordV3};
+
+// Lock rules, a yaml file like
+//
+// rules:
+//   - set: [EmblemOfSeveredFate]
+//     slot: [sands, goblet, circlet]
+//     min_crit_value: 30
+//     action: lock
+//   - max_level: 0
+//     equipped: false
+//     action: unlock
+//
+// Keys are the GOOD ones. The first rule whose filters all match decides what
+// happens to an artifact, artifacts no rule matches are kept as they are.
+
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RuleAction {
+    Lock,
+    Unlock,
+    Keep,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct LockRule {
+    pub action: RuleAction,
+    pub set: Option<Vec<String>>,
+    pub slot: Option<Vec<String>>,
+    pub main_stat: Option<Vec<String>>,
+    pub rarity: Option<Vec<u32>>,
+    pub min_level: Option<u32>,
+    pub max_level: Option<u32>,
+    // crit rate * 2 + crit damage of the substats
+    pub min_crit_value: Option<f64>,
+    pub location: Option<Vec<String>>,
+    pub equipped: Option<bool>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct LockRules {
+    pub rules: Vec<LockRule>,
+}
+
+fn crit_value(artifact: &InternalArtifact) -> f64 {
+    artifact
+        .sub_stats()
+        .iter()
+        .map(|s| match s.key {
+            ArtifactStatKey::Critical => s.value * 2.0,
+            ArtifactStatKey::CriticalDamage => s.value,
+            _ => 0.0,
+        })
+        .sum()
+}
+
+fn check_keys<T>(keys: &Option<Vec<String>>, parse: fn(&str) -> Option<T>) -> Result<()> {
+    for key in keys.iter().flatten() {
+        parse(key).ok_or_else(|| anyhow!("unknown key `{}`", key))?;
+    }
+    Ok(())
+}
+
+// an unset filter matches everything
+fn contains<T: PartialEq>(list: &Option<Vec<T>>, value: &T) -> bool {
+    match list {
+        Some(list) => list.contains(value),
+        None => true,
+    }
+}
+
+impl LockRule {
+    fn check(&self) -> Result<()> {
+        check_keys(&self.set, ArtifactSetKey::from_good)?;
+        check_keys(&self.slot, ArtifactSlotKey::from_good)?;
+        check_keys(&self.main_stat, ArtifactStatKey::from_good)?;
+        check_keys(&self.location, CharacterKey::from_good)?;
+        Ok(())
+    }
+
+    pub fn matches(&self, artifact: &InternalArtifact) -> bool {
+        let location = artifact.location.as_ref().map(|c| c.to_good());
+        contains(&self.set, &artifact.set_key.to_good())
+            && contains(&self.slot, &artifact.slot_key.to_good())
+            && contains(&self.main_stat, &artifact.main_stat.key.to_good())
+            && contains(&self.rarity, &artifact.rarity)
+            && self.min_level.iter().all(|&l| artifact.level >= l)
+            && self.max_level.iter().all(|&l| artifact.level <= l)
+            && self
+                .min_crit_value
+                .iter()
+                .all(|&cv| crit_value(artifact) >= cv)
+            && match (&self.location, &location) {
+                (Some(keys), Some(key)) => keys.contains(key),
+                (Some(_), None) => false,
+                (None, _) => true,
+            }
+            && self.equipped.iter().all(|&e| location.is_some() == e)
+    }
+}
+
+impl LockRules {
+    pub fn from_yaml(yaml_str: &str) -> Result<LockRules> {
+        let rules: LockRules = serde_yaml::from_str(yaml_str)?;
+        for (i, rule) in rules.rules.iter().enumerate() {
+            rule.check()
+                .map_err(|e| anyhow!("lock rule {}: {}", i + 1, e))?;
+        }
+        Ok(rules)
+    }
+
+    pub fn from_file(path: &Path) -> Result<LockRules> {
+        let yaml_str = fs::read_to_string(path)
+            .map_err(|e| anyhow!("cannot read {}: {}", path.display(), e))?;
+        Self::from_yaml(&yaml_str)
+    }
+
+    pub fn action(&self, artifact: &InternalArtifact) -> RuleAction {
+        self.rules
+            .iter()
+            .find(|r| r.matches(artifact))
+            .map_or(RuleAction::Keep, |r| r.action)
+    }
+
+    // `indices` are the backpack indices of `artifacts`. Only artifacts whose
+    // lock has to change are listed, each with its fingerprint and a
+    // validation of the lock state seen while scanning, so that a shifted
+    // backpack skips or stops the lock run.
+    pub fn evaluate(&self, artifacts: &[InternalArtifact], indices: &[usize]) -> LockFormatV3 {
+        let mut data = LockFormatV3 {
+            version: 3,
+            flip: Vec::new(),
+            lock: Vec::new(),
+            unlock: Vec::new(),
+            validation: Vec::new(),
+        };
+        for (artifact, &index) in artifacts.iter().zip(indices.iter()) {
+            let index = index as u32;
+            let target = LockTargetV3 {
+                index,
+                artifact: ArtifactFingerprint::from_artifact(artifact),
+            };
+            match self.action(artifact) {
+                RuleAction::Lock if !artifact.lock => data.lock.push(target),
+                RuleAction::Unlock if artifact.lock => data.unlock.push(target),
+                _ => continue,
+            }
+            data.validation.push(LockValidationRecordV3 {
+                index,
+                locked: artifact.lock,
+                artifact: ArtifactFingerprint::from_artifact(artifact),
+            });
+        }
+        info!(
+            "lock rules: {} to lock, {} to unlock",
+            data.lock.len(),
+            data.unlock.len()
+        );
+        data
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::artifact::internal_artifact::ArtifactStat;
+    use crate::lock::{LockAction, LockActionType};
+
+    fn artifact(lock: bool, level: u32) -> InternalArtifact {
+        InternalArtifact {
+            set_key: ArtifactSetKey::GladiatorsFinale,
+            slot_key: ArtifactSlotKey::Flower,
+            rarity: 5,
+            level,
+            lock,
+            location: None,
+            main_stat: ArtifactStat {
+                key: ArtifactStatKey::Hp,
+                value: 4780.0,
+            },
+            sub_stat_1: None,
+            sub_stat_2: None,
+            sub_stat_3: None,
+            sub_stat_4: None,
+        }
+    }
+
+    #[test]
+    fn evaluate_writes_v3() {
+        let rules = LockRules::from_yaml("rules:\n  - max_level: 0\n    action: unlock\n").unwrap();
+        let artifacts = vec![artifact(true, 20), artifact(true, 0), artifact(false, 0)];
+        let json = serde_json::to_string(&rules.evaluate(&artifacts, &[0, 2, 5])).unwrap();
+
+        let actions = LockAction::from_lock_json(&json).unwrap();
+        let types: Vec<(u32, LockActionType)> = actions
+            .iter()
+            .map(|a| (a.target, a.type_.clone()))
+            .collect();
+        assert_eq!(
+            types,
+            vec![
+                (2, LockActionType::Unlock),
+                (2, LockActionType::ValidateLocked)
+            ]
+        );
+        assert!(actions.iter().all(|a| a.fingerprint.is_some()));
+    }
+}
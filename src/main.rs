@@ -1,26 +1,37 @@
 use anyhow::{anyhow, Result};
+use std::collections::BTreeMap;
 use std::fs;
 use std::io::stdin;
 use std::io::stdout;
 use std::io::Write;
 use std::net::TcpStream;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use std::time::SystemTime;
 use tungstenite::WebSocket;
-use yas::artifact::internal_artifact::InternalArtifact;
-use yas::lock::LockAction;
-use yas::ws::packet::{ConfigNotifyData, LockRspData, ScanRspData};
+use yas::artifact::names;
+use yas::lock::dry_run::{self, DryRunReport};
+use yas::lock::journal::LockJournal;
+use yas::lock::rules::LockRules;
+use yas::lock::{LockAction, LockReport};
+use yas::ws::packet::{ConfigNotifyData, DryRunRspData, LockRspData, ScanRspData};
 
 use yas::capture::capture_absolute_image;
-use yas::common::utils;
-use yas::expo::genmo::GenmoFormat;
-use yas::expo::good::GoodFormat;
-use yas::expo::mona::MonaFormat;
+use yas::capture::screen_source::FileSource;
+use yas::common::{utils, RawImage};
+use yas::expo;
+use yas::expo::good;
+use yas::expo::{ExportOptions, ExportTarget};
+use yas::inference::inference::{CRNNModel, ModelPaths, TextModel};
+use yas::inference::pre_process::{image_to_raw, pre_process};
 use yas::info::info;
+use yas::scanner::artifact_recognizer::{
+    dump_unrecognized, recognize_dir, recognized_artifacts, recognized_indices, ArtifactRecord,
+    ScanSummary,
+};
 use yas::scanner::yas_scanner::{YasScanner, YasScannerConfig};
 use yas::ws::packet::Packet;
 
-use clap::{arg, value_parser, ArgMatches, Command};
+use clap::{arg, value_parser, ArgAction, ArgMatches, Command};
 use env_logger::Builder;
 use log::{error, info, warn, LevelFilter};
 
@@ -51,6 +62,44 @@ fn get_cli() -> Command {
         .arg(arg!(--"no-check" "不检测是否已打开背包等"))
         .arg(arg!(--"dxgcap" "使用dxgcap捕获屏幕"))
         .arg(arg!(--"gui" "开启Web GUI"))
+        .arg(
+            arg!(--"model" <FILE> "使用指定的onnx模型代替内置模型")
+                .required(false)
+                .global(true),
+        )
+        .arg(
+            arg!(--"vocab" <FILE> "使用指定的字表(index_2_word.json)代替内置字表")
+                .required(false)
+                .global(true),
+        )
+        .arg(
+            arg!(--"names" <FILE> "圣遗物和角色名称的补充文件(json/yaml)，可以添加或修改中文名，也可以用GOOD的键名添加新版本的套装和角色")
+                .required(false)
+                .global(true),
+        )
+        .arg(
+            arg!(--"format" <FORMAT> "导出格式，可多次指定，格式为NAME或NAME=FILE（mona/genmo/good/csv），默认导出mona/genmo/good")
+                .action(ArgAction::Append)
+                .global(true),
+        )
+        .arg(
+            arg!(--"good-rolls" "在good.json中输出副词条的强化次数分解（非GOOD标准字段）")
+                .global(true),
+        )
+        .arg(
+            arg!(--"good-extra" "在good.json中输出背包序号index和主词条数值mainStatValue（非GOOD标准字段）")
+                .global(true),
+        )
+        .arg(
+            arg!(--"lock-rules" <FILE> "扫描后按规则文件(yaml)生成lock.json，下次运行时可直接加解锁")
+                .required(false),
+        )
+        .arg(arg!(--"record" <DIR> "录制所有截图到指定目录，debug专用").required(false))
+        .arg(arg!(--"replay" <DIR> "使用录制的截图代替屏幕截图，debug专用").required(false))
+        .arg(
+            arg!(--"save-panels" <DIR> "保存圣遗物详情截图到指定目录，可用recognize-dir重新识别")
+                .required(false),
+        )
         .arg(
             arg!(--"max-row" <ROW> "最大扫描行数")
                 .default_value("1000")
@@ -106,6 +155,22 @@ fn get_cli() -> Command {
                 .default_value("0")
                 .value_parser(value_parser!(u32)),
         )
+        .arg(
+            arg!(--"max-wait-late-flip" <TIME> "每页加解锁后等待未生效的圣遗物的最大时间(ms)，超时才重试")
+                .default_value("500")
+                .value_parser(value_parser!(u32)),
+        )
+        .arg(
+            arg!(--"flip-check-stop" <TIME> "等待加解锁生效时每次检查的间隔(ms)")
+                .default_value("50")
+                .value_parser(value_parser!(u32)),
+        )
+        .arg(arg!(--"resume" "继续上次中断的加解锁：lock_journal.jsonl中已点过的圣遗物只在状态不对时再点"))
+        .arg(
+            arg!(--"lock-retry" <COUNT> "每页加解锁后检查锁定状态，未生效时的最大重试次数")
+                .default_value("2")
+                .value_parser(value_parser!(u32)),
+        )
         .arg(
             arg!(--"offset-x" <OFFSET> "人为指定横坐标偏移（截图有偏移时可用该选项校正）")
                 .default_value("0")
@@ -120,14 +185,72 @@ fn get_cli() -> Command {
             arg!(--"window" <NAME> "原神窗口名")
                 .default_value("原神"),
         )
+        .arg(
+            arg!(--"min-confidence" <CONF> "识别置信度低于该值时提示手动检查（0-1）")
+                .default_value("0.9")
+                .value_parser(value_parser!(f32)),
+        )
         .arg(
             arg!(--"scroll-speed" <SPEED> "滚轮速度（单位：像素，仅在云原神模式下生效）")
                 .default_value("15.0")
                 .value_parser(value_parser!(f64)),
         )
+        .subcommand(
+            Command::new("ocr")
+                .about("对图片运行文字识别，输出文件名到识别结果的JSON，debug专用")
+                .arg(arg!(<PATH> ... "图片或包含png图片的目录，例如dumps"))
+                .arg(arg!(--"output" <FILE> "输出JSON文件，默认打印到标准输出").required(false))
+                .arg(
+                    arg!(--"batch" <SIZE> "每次推理的图片数量，可用于测试批量推理的速度")
+                        .default_value("36")
+                        .value_parser(value_parser!(usize)),
+                ),
+        )
+        .subcommand(
+            Command::new("recognize-dir")
+                .about("从--save-panels保存的截图重新识别圣遗物，输出mona/genmo/good")
+                .arg(arg!(<DIR> "截图目录"))
+                .arg(arg!(--"output-dir" <DIR> "输出目录").default_value("."))
+                .arg(arg!(--"dump" "输出模型预测结果、二值化图像和灰度图像，debug专用"))
+                .arg(
+                    arg!(--"min-level" <LEVEL> "最小等级")
+                        .default_value("0")
+                        .value_parser(value_parser!(u32)),
+                )
+                .arg(
+                    arg!(--"min-confidence" <CONF> "识别置信度低于该值时提示手动检查（0-1）")
+                        .default_value("0.9")
+                        .value_parser(value_parser!(f32)),
+                ),
+        )
+        .subcommand(
+            Command::new("dry-run")
+                .about("用扫描结果模拟加解锁，列出将会加锁、解锁的圣遗物和验证失败项，不操作游戏")
+                .arg(arg!(<LOCK> "lock.json"))
+                .arg(
+                    arg!(--"scan" <FILE> "扫描得到的good.json，同目录的unrecognized.json会一并读取")
+                        .default_value("good.json"),
+                )
+                .arg(
+                    arg!(--"window-size" <SIZE> "游戏窗口大小，用于计算翻页次数")
+                        .default_value("1920x1080"),
+                )
+                .arg(arg!(--"output" <FILE> "输出JSON文件，默认打印到标准输出").required(false)),
+        )
+        .subcommand(
+            Command::new("convert")
+                .about("读取mona/genmo/good格式的导出文件，转换为全部三种格式")
+                .arg(arg!(<INPUT> "mona.json、genmo.json或good.json"))
+                .arg(arg!(--"output-dir" <DIR> "输出目录").default_value("converted")),
+        )
 }
 
 fn get_info(matches: &ArgMatches) -> Result<info::ScanInfo> {
+    if let Some(dir) = matches.get_one::<String>("replay") {
+        let rect = FileSource::window_rect(Path::new(dir))?;
+        return info::ScanInfo::from_rect(&rect).map_err(|e| anyhow!(e));
+    }
+
     utils::set_dpi_awareness();
 
     let window_name: String = matches.get_one::<String>("window").unwrap().to_string();
@@ -172,10 +295,23 @@ fn get_info(matches: &ArgMatches) -> Result<info::ScanInfo> {
     Ok(info)
 }
 
-fn do_scan(matches: ArgMatches) -> Result<Vec<InternalArtifact>> {
+fn do_scan(matches: ArgMatches) -> Result<Vec<ArtifactRecord>> {
     let config = YasScannerConfig::from_match(&matches)?;
     let info = get_info(&matches)?;
     let output_dir = Path::new(matches.try_get_one::<String>("output-dir")?.unwrap());
+    // before scanning, so that a typo in --format or the rules fails early
+    let targets = export_targets(&matches)?;
+    let rules = match matches.get_one::<String>("lock-rules") {
+        Some(path) => Some(LockRules::from_file(Path::new(path))?),
+        None => None,
+    };
+    let lock_path = output_dir.join("lock.json");
+    if rules.is_some() && lock_path.exists() {
+        return Err(anyhow!(
+            "{}已存在，请先移走或换一个--output-dir",
+            lock_path.display()
+        ));
+    }
 
     let mut scanner = YasScanner::new(info.clone(), config)?;
 
@@ -184,29 +320,197 @@ fn do_scan(matches: ArgMatches) -> Result<Vec<InternalArtifact>> {
     let t = now.elapsed()?.as_secs_f64();
     info!("time: {}s", t);
 
-    // Mona
-    let mona = MonaFormat::new(&results);
-    utils::dump_json(&mona, output_dir.join("mona.json"))?;
-    // Genmo
-    let genmo = GenmoFormat::new(&results);
-    utils::dump_json(&genmo, output_dir.join("genmo.json"))?;
-    // GOOD
-    let good = GoodFormat::new(&results);
-    utils::dump_json(&good, output_dir.join("good.json"))?;
-
+    export(&results, output_dir, &targets)?;
+    if let Some(rules) = rules {
+        let lock = rules.evaluate(
+            &recognized_artifacts(&results),
+            &recognized_indices(&results),
+        );
+        utils::dump_json(&lock, lock_path)?;
+    }
     Ok(results)
 }
 
-fn do_lock(matches: ArgMatches, actions: Vec<LockAction>) -> Result<()> {
+fn export_targets(matches: &ArgMatches) -> Result<Vec<ExportTarget>> {
+    let specs: Vec<String> = matches
+        .get_many::<String>("format")
+        .map(|v| v.cloned().collect())
+        .unwrap_or_default();
+    let options = ExportOptions {
+        good_rolls: matches.get_flag("good-rolls"),
+        good_extra: matches.get_flag("good-extra"),
+    };
+    ExportTarget::from_specs(&specs, &options)
+}
+
+fn export(records: &[ArtifactRecord], output_dir: &Path, targets: &[ExportTarget]) -> Result<()> {
+    expo::export_all(
+        targets,
+        &recognized_artifacts(records),
+        &recognized_indices(records),
+        output_dir,
+    )?;
+    // slots left out of the exports, by backpack index
+    dump_unrecognized(records, output_dir.join("unrecognized.json"))?;
+    utils::dump_json(
+        &ScanSummary::new(records),
+        output_dir.join("scan_summary.json"),
+    )
+}
+
+fn do_convert(matches: &ArgMatches) -> Result<()> {
+    let input = Path::new(matches.get_one::<String>("INPUT").unwrap());
+    let output_dir = Path::new(matches.get_one::<String>("output-dir").unwrap());
+    let targets = export_targets(matches)?;
+
+    let results = expo::import_file(input)?;
+    fs::create_dir_all(output_dir)?;
+    let indices: Vec<usize> = (0..results.len()).collect();
+    expo::export_all(&targets, &results, &indices, output_dir)?;
+    Ok(())
+}
+
+fn do_recognize_dir(matches: &ArgMatches) -> Result<()> {
+    let dir = Path::new(matches.get_one::<String>("DIR").unwrap());
+    let output_dir = Path::new(matches.get_one::<String>("output-dir").unwrap());
+    let min_level: u32 = *matches.get_one("min-level").unwrap();
+    let min_confidence: f32 = *matches.get_one("min-confidence").unwrap();
+
+    let results = recognize_dir(
+        dir,
+        &model_paths(matches),
+        matches.get_flag("dump"),
+        min_level,
+        min_confidence,
+    )?;
+    export(&results, output_dir, &export_targets(matches)?)
+}
+
+// lock_json wins over a v1 index list
+fn lock_actions(lock_json: &Option<String>, indices: &Option<Vec<u32>>) -> Result<Vec<LockAction>> {
+    Ok(match lock_json {
+        Some(json_str) => LockAction::from_lock_json(&json_str)?,
+        None => match indices {
+            Some(indices) => LockAction::from_v1(&indices),
+            None => Vec::new(),
+        },
+    })
+}
+
+fn do_dry_run(matches: &ArgMatches) -> Result<()> {
+    let lock_path = Path::new(matches.get_one::<String>("LOCK").unwrap());
+    let json_str = fs::read_to_string(lock_path)
+        .map_err(|e| anyhow!("cannot read {}: {}", lock_path.display(), e))?;
+    let actions = LockAction::from_lock_json(&json_str)?;
+    let (backpack, art_count) =
+        dry_run::load_scan(Path::new(matches.get_one::<String>("scan").unwrap()))?;
+    let (rows, cols) = dry_run::grid_of_window(matches.get_one::<String>("window-size").unwrap())?;
+
+    let report = dry_run::simulate(&actions, &backpack, art_count, rows, cols);
+    report.log();
+    match matches.get_one::<String>("output") {
+        Some(output) => utils::dump_json(&report, PathBuf::from(output)),
+        None => {
+            println!("{}", serde_json::to_string_pretty(&report)?);
+            Ok(())
+        }
+    }
+}
+
+fn do_lock(matches: ArgMatches, actions: Vec<LockAction>) -> Result<LockReport> {
     let config = YasScannerConfig::from_match(&matches)?;
+    let output_dir = Path::new(matches.get_one::<String>("output-dir").unwrap());
+    let journal_path = output_dir.join("lock_journal.jsonl");
+    // before touching the game, so that a missing journal fails early
+    let (actions, journal) = if matches.get_flag("resume") {
+        let done = LockJournal::load(&journal_path)?;
+        info!("继续上次的加解锁，已完成{}个", done.len());
+        (
+            LockAction::resume(actions, &done)?,
+            LockJournal::append(&journal_path)?,
+        )
+    } else {
+        (actions, LockJournal::create(&journal_path)?)
+    };
     let info = get_info(&matches)?;
 
     let mut scanner = YasScanner::new(info.clone(), config)?;
-    scanner.lock(actions)
+    let report = scanner.lock(actions, Some(journal))?;
+    // kept for --resume if anything went wrong
+    if report.ok() {
+        LockJournal::finish(&journal_path)?;
+    }
+    Ok(report)
+}
+
+fn model_paths(matches: &ArgMatches) -> ModelPaths {
+    ModelPaths {
+        model: matches.get_one::<String>("model").map(PathBuf::from),
+        vocab: matches.get_one::<String>("vocab").map(PathBuf::from),
+    }
+}
+
+fn do_ocr(matches: &ArgMatches) -> Result<()> {
+    let mut files: Vec<PathBuf> = Vec::new();
+    for path in matches.get_many::<String>("PATH").unwrap() {
+        let path = Path::new(path);
+        if path.is_dir() {
+            let mut pngs: Vec<PathBuf> = fs::read_dir(path)?
+                .filter_map(|e| e.ok())
+                .map(|e| e.path())
+                .filter(|p| p.extension().map_or(false, |ext| ext == "png"))
+                .collect();
+            pngs.sort();
+            files.extend(pngs);
+        } else {
+            files.push(path.to_path_buf());
+        }
+    }
+
+    let mut results: BTreeMap<String, String> = BTreeMap::new();
+    let mut images = Vec::new();
+    for file in files {
+        let im = image::open(&file)
+            .map_err(|e| anyhow!("cannot open {}: {}", file.display(), e))?
+            .to_luma8();
+        let im = pre_process(image_to_raw(im));
+        if im.w == 0 || im.h == 0 {
+            results.insert(file.to_string_lossy().to_string(), String::new());
+        } else {
+            images.push((file, im));
+        }
+    }
+
+    let model = CRNNModel::load(&model_paths(matches))?;
+    let batch_size: usize = *matches.get_one("batch").unwrap();
+    let now = SystemTime::now();
+    for chunk in images.chunks(batch_size.max(1)) {
+        let batch: Vec<&RawImage> = chunk.iter().map(|(_, im)| im).collect();
+        for ((file, _), r) in chunk.iter().zip(model.inference_batch(&batch)?) {
+            info!("{}: {} ({:.3})", file.display(), r.text, r.confidence);
+            results.insert(file.to_string_lossy().to_string(), r.text);
+        }
+    }
+    let t = now.elapsed()?.as_secs_f64();
+    info!(
+        "{} images in {:.3}s, {:.1} images/s (batch size {})",
+        images.len(),
+        t,
+        images.len() as f64 / t,
+        batch_size
+    );
+
+    match matches.get_one::<String>("output") {
+        Some(output) => utils::dump_json(&results, PathBuf::from(output)),
+        None => {
+            println!("{}", serde_json::to_string_pretty(&results)?);
+            Ok(())
+        }
+    }
 }
 
 fn run_once(matches: ArgMatches) -> Result<()> {
-    let output_dir = Path::new(matches.get_one::<String>("output-dir").unwrap());
+    let output_dir = PathBuf::from(matches.get_one::<String>("output-dir").unwrap());
 
     let mut lock_mode = false;
     let mut actions: Vec<LockAction> = Vec::new();
@@ -226,7 +530,8 @@ fn run_once(matches: ArgMatches) -> Result<()> {
 
     // let _ = scanner.test()?;
     if lock_mode {
-        do_lock(matches, actions)
+        let report = do_lock(matches, actions)?;
+        utils::dump_json(&report, output_dir.join("lock_report.json"))
     } else {
         do_scan(matches).map(|_| ())
     }
@@ -268,9 +573,14 @@ fn run_ws(matches: ArgMatches) -> Result<()> {
                 } else {
                     info!("recieved: {}", pkt.name());
                 }
+                let mut argv = p.argv.clone();
+                for format in p.formats.iter().flatten() {
+                    argv.push(String::from("--format"));
+                    argv.push(format.clone());
+                }
                 let matches = get_cli()
                     .no_binary_name(true)
-                    .try_get_matches_from(p.argv.iter())?;
+                    .try_get_matches_from(argv.iter())?;
                 Ok(Some(ScanRspData::packet(do_scan(matches))?))
             }
             Packet::LockReq(p) => {
@@ -282,15 +592,30 @@ fn run_ws(matches: ArgMatches) -> Result<()> {
                 let matches = get_cli()
                     .no_binary_name(true)
                     .try_get_matches_from(p.argv.iter())?;
-                let actions = match &p.lock_json {
-                    Some(json_str) => LockAction::from_lock_json(&json_str)?,
-                    None => match &p.indices {
-                        Some(indices) => LockAction::from_v1(&indices),
-                        None => Vec::new(),
-                    },
-                };
+                let actions = lock_actions(&p.lock_json, &p.indices)?;
                 Ok(Some(LockRspData::packet(do_lock(matches, actions))?))
             }
+            Packet::DryRunReq(p) => {
+                if verbose {
+                    info!("recieved: {:?}", pkt);
+                } else {
+                    info!("recieved: {}", pkt.name());
+                }
+                let report = || -> Result<DryRunReport> {
+                    let actions = lock_actions(&p.lock_json, &p.indices)?;
+                    let backpack = dry_run::backpack(
+                        good::import_good_indexed(&p.good_json)?,
+                        &p.unrecognized_indices,
+                    )?;
+                    let art_count = dry_run::art_count(&backpack, p.art_count)?;
+                    let window_size = p.window_size.as_deref().unwrap_or("1920x1080");
+                    let (rows, cols) = dry_run::grid_of_window(window_size)?;
+                    let report = dry_run::simulate(&actions, &backpack, art_count, rows, cols);
+                    report.log();
+                    Ok(report)
+                };
+                Ok(Some(DryRunRspData::packet(report())?))
+            }
             p => {
                 warn!("unexpected packet: {}", p.name());
                 Err(anyhow!("unexpected packet"))
@@ -340,6 +665,18 @@ fn run_ws(matches: ArgMatches) -> Result<()> {
 }
 
 fn start(matches: ArgMatches) -> Result<()> {
+    if let Some(path) = matches.get_one::<String>("names") {
+        names::install_override(Path::new(path))?;
+    }
+
+    match matches.subcommand() {
+        Some(("ocr", sub_matches)) => return do_ocr(sub_matches),
+        Some(("recognize-dir", sub_matches)) => return do_recognize_dir(sub_matches),
+        Some(("convert", sub_matches)) => return do_convert(sub_matches),
+        Some(("dry-run", sub_matches)) => return do_dry_run(sub_matches),
+        _ => {}
+    }
+
     if !utils::is_admin() {
         return Err(anyhow!("请以管理员身份运行该程序"));
     }
@@ -367,7 +704,11 @@ fn main() {
         .format_timestamp_millis()
         .init();
 
+    let interactive = matches.subcommand().is_none();
     start(matches).unwrap_or_else(|e| error!("{:#}", e));
+    if !interactive {
+        return;
+    }
 
     info!("按Enter退出");
     let mut s = String::new();
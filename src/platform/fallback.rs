@@ -0,0 +1,40 @@
+use anyhow::{anyhow, Result};
+
+use crate::common::PixelRect;
+
+#[derive(Clone, Copy, Debug)]
+pub struct WindowHandle;
+
+const UNSUPPORTED: &str = "only supported on Windows";
+
+pub fn find_ys_window() -> Result<WindowHandle, String> {
+    Err(format!("window lookup is {}", UNSUPPORTED))
+}
+
+pub fn find_window_by_name(_name: &str) -> Result<WindowHandle, String> {
+    Err(format!("window lookup is {}", UNSUPPORTED))
+}
+
+pub fn get_client_rect(_hwnd: WindowHandle) -> Result<PixelRect> {
+    Err(anyhow!("window rect is {}", UNSUPPORTED))
+}
+
+pub fn is_admin() -> bool {
+    false
+}
+
+pub fn is_rmb_down() -> bool {
+    false
+}
+
+pub fn is_f12_down() -> bool {
+    false
+}
+
+pub fn set_dpi_awareness() {}
+
+pub fn show_window_and_set_foreground(_hwnd: WindowHandle) {}
+
+pub fn capture_absolute(_rect: &PixelRect) -> Result<Vec<u8>> {
+    Err(anyhow!("screen capture is {}", UNSUPPORTED))
+}
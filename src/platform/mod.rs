@@ -0,0 +1,14 @@
+// Everything that talks to the OS: window lookup, screen capture through GDI,
+// key state and process privileges. Only Windows is supported, other targets
+// get a fallback that fails at runtime, so that the pure parts of the crate
+// (lock, expo, inference, ...) still build there.
+
+#[cfg(windows)]
+mod windows;
+#[cfg(windows)]
+pub use self::windows::*;
+
+#[cfg(not(windows))]
+mod fallback;
+#[cfg(not(windows))]
+pub use self::fallback::*;
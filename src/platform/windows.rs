@@ -0,0 +1,296 @@
+use anyhow::{anyhow, Result};
+use std::ffi::OsStr;
+use std::iter::once;
+use std::mem::{size_of, transmute};
+use std::os::windows::ffi::OsStrExt;
+use std::ptr::null_mut;
+
+use log::{info, warn};
+use winapi::ctypes::c_void;
+use winapi::shared::minwindef::BOOL;
+use winapi::shared::windef::{HBITMAP, HDC, HWND, POINT as WinPoint, RECT as WinRect};
+use winapi::um::libloaderapi::{FreeLibrary, GetProcAddress, LoadLibraryA};
+use winapi::um::securitybaseapi::{AllocateAndInitializeSid, CheckTokenMembership, FreeSid};
+use winapi::um::wingdi::{
+    BitBlt, CreateCompatibleBitmap, CreateCompatibleDC, DeleteObject, GetDIBits, GetObjectW,
+    SelectObject, BITMAP, BITMAPINFO, BITMAPINFOHEADER, BI_RGB, DIB_RGB_COLORS, SRCCOPY,
+};
+use winapi::um::winnt::{
+    DOMAIN_ALIAS_RID_ADMINS, PSID, SECURITY_BUILTIN_DOMAIN_RID, SECURITY_NT_AUTHORITY,
+    SID_IDENTIFIER_AUTHORITY,
+};
+use winapi::um::winuser::{
+    ClientToScreen, FindWindowW, GetAsyncKeyState, GetClientRect, GetDC, ReleaseDC,
+    SetForegroundWindow, SetProcessDPIAware, ShowWindow, SW_RESTORE, VK_F12, VK_RBUTTON,
+};
+
+use crate::common::PixelRect;
+
+pub type WindowHandle = HWND;
+
+pub fn encode_wide(s: String) -> Vec<u16> {
+    let wide: Vec<u16> = OsStr::new(&s).encode_wide().chain(once(0)).collect();
+    wide
+}
+
+fn find_window(p_class_name: *const u16, p_window_name: *const u16) -> Result<HWND, String> {
+    let result: HWND = unsafe { FindWindowW(p_class_name, p_window_name) };
+    if result.is_null() {
+        Err(String::from("cannot find window"))
+    } else {
+        Ok(result)
+    }
+}
+
+pub fn find_ys_window() -> Result<WindowHandle, String> {
+    let class_name = encode_wide(String::from("UnityWndClass"));
+    let window_name = encode_wide(String::from("原神"));
+    find_window(class_name.as_ptr(), window_name.as_ptr())
+}
+
+pub fn find_window_by_name(name: &str) -> Result<WindowHandle, String> {
+    let window_name = encode_wide(String::from(name));
+    find_window(null_mut(), window_name.as_ptr())
+}
+
+unsafe fn get_client_rect_unsafe(hwnd: WindowHandle) -> Result<PixelRect> {
+    let mut rect: WinRect = WinRect {
+        left: 0,
+        top: 0,
+        right: 0,
+        bottom: 0,
+    };
+    GetClientRect(hwnd, &mut rect);
+    let width: i32 = rect.right;
+    let height: i32 = rect.bottom;
+
+    let mut point: WinPoint = WinPoint { x: 0, y: 0 };
+    ClientToScreen(hwnd, &mut point as *mut WinPoint);
+    let left: i32 = point.x;
+    let top: i32 = point.y;
+
+    Ok(PixelRect {
+        left,
+        top,
+        width,
+        height,
+    })
+}
+
+pub fn get_client_rect(hwnd: WindowHandle) -> Result<PixelRect> {
+    unsafe { get_client_rect_unsafe(hwnd) }
+}
+
+unsafe fn is_admin_unsafe() -> bool {
+    let mut authority: SID_IDENTIFIER_AUTHORITY = SID_IDENTIFIER_AUTHORITY {
+        Value: SECURITY_NT_AUTHORITY,
+    };
+    let mut group: PSID = null_mut();
+    let mut b = AllocateAndInitializeSid(
+        &mut authority as *mut SID_IDENTIFIER_AUTHORITY,
+        2,
+        SECURITY_BUILTIN_DOMAIN_RID,
+        DOMAIN_ALIAS_RID_ADMINS,
+        0,
+        0,
+        0,
+        0,
+        0,
+        0,
+        &mut group as *mut PSID,
+    );
+    if b != 0 {
+        let r = CheckTokenMembership(null_mut(), group, &mut b as *mut BOOL);
+        if r == 0 {
+            b = 0;
+        }
+        FreeSid(group);
+    }
+
+    b != 0
+}
+
+pub fn is_admin() -> bool {
+    unsafe { is_admin_unsafe() }
+}
+
+pub fn is_rmb_down() -> bool {
+    unsafe {
+        let state = GetAsyncKeyState(VK_RBUTTON);
+        if state == 0 {
+            return false;
+        }
+
+        state & 1 > 0
+    }
+}
+
+pub fn is_f12_down() -> bool {
+    unsafe {
+        let state = GetAsyncKeyState(VK_F12);
+        if state == 0 {
+            return false;
+        }
+
+        state & 1 > 0
+    }
+}
+
+pub fn encode_lpcstr(s: &str) -> Vec<i8> {
+    let mut arr: Vec<i8> = s.bytes().map(|x| x as i8).collect();
+    arr.push(0);
+    arr
+}
+
+pub fn set_dpi_awareness() {
+    // let os = os_info::get();
+
+    let h_lib = unsafe {
+        // let names = ["SHCore.dll"]
+
+        LoadLibraryA(encode_lpcstr("Shcore.dll").as_ptr())
+    };
+    if h_lib.is_null() {
+        info!("`Shcore.dll` not found");
+        unsafe {
+            SetProcessDPIAware();
+        }
+    } else {
+        info!("`Shcore.dll` found");
+        unsafe {
+            let addr = GetProcAddress(h_lib, encode_lpcstr("SetProcessDpiAwareness").as_ptr());
+            if addr.is_null() {
+                warn!("cannot find process `SetProcessDpiAwareness`, but `Shcore.dll` exists");
+                SetProcessDPIAware();
+            } else {
+                // func(PROCESS_DPI_AWARENESS) -> HRESULT
+                let func = transmute::<*const (), fn(u32) -> i32>(addr as *const ());
+                // SetProcessDpiAwareness(PROCESS_PER_MONITOR_DPI_AWARE);
+                func(2);
+            }
+
+            FreeLibrary(h_lib);
+        }
+    }
+
+    // if os.version() >= &os_info::Version::from_string("8.1") {
+    //     info!("Windows version >= 8.1");
+    //     unsafe {
+    //         SetProcessDpiAwareness(PROCESS_PER_MONITOR_DPI_AWARE);
+    //     }
+    // } else {
+    //     info!("Windows version < 8.1");
+    //     unsafe {
+    //         SetProcessDPIAware();
+    //     }
+    // }
+}
+
+pub fn show_window_and_set_foreground(hwnd: WindowHandle) {
+    unsafe {
+        ShowWindow(hwnd, SW_RESTORE);
+        SetForegroundWindow(hwnd);
+    }
+}
+
+unsafe fn unsafe_capture(rect: &PixelRect) -> Result<Vec<u8>> {
+    // SetThreadDpiAwarenessContext(DPI_AWARENESS_CONTEXT_SYSTEM_AWARE);
+
+    let dc_window: HDC = GetDC(null_mut());
+
+    let dc_mem: HDC = CreateCompatibleDC(dc_window);
+    if dc_mem.is_null() {
+        return Err(anyhow!("CreateCompatibleDC Failed"));
+    }
+
+    let hbm: HBITMAP = CreateCompatibleBitmap(dc_window, rect.width, rect.height);
+    if hbm.is_null() {
+        return Err(anyhow!("CreateCompatibleBitmap failed"));
+    }
+
+    SelectObject(dc_mem, hbm as *mut c_void);
+
+    let result = BitBlt(
+        dc_mem,
+        0,
+        0,
+        rect.width,
+        rect.height,
+        dc_window,
+        rect.left,
+        rect.top,
+        SRCCOPY,
+    );
+    if result == 0 {
+        return Err(anyhow!("BitBlt failed"));
+    }
+
+    let mut bitmap: BITMAP = BITMAP {
+        bmBits: 0 as *mut c_void,
+        bmBitsPixel: 0,
+        bmPlanes: 0,
+        bmWidthBytes: 0,
+        bmHeight: 0,
+        bmWidth: 0,
+        bmType: 0,
+    };
+    GetObjectW(
+        hbm as *mut c_void,
+        size_of::<BITMAP>() as i32,
+        (&mut bitmap) as *mut BITMAP as *mut c_void,
+    );
+    // println!("bitmap width: {}", bitmap.bmWidth);
+    // println!("bitmap height: {}", bitmap.bmHeight);
+    // println!("bitmap bits pixel: {}", bitmap.bmBitsPixel);
+
+    let mut bi: BITMAPINFOHEADER = BITMAPINFOHEADER {
+        biSize: size_of::<BITMAPINFOHEADER>() as u32,
+        biWidth: bitmap.bmWidth,
+        biHeight: bitmap.bmHeight,
+        biPlanes: 1,
+        biBitCount: 32,
+        biCompression: BI_RGB,
+        biSizeImage: 0,
+        biXPelsPerMeter: 0,
+        biYPelsPerMeter: 0,
+        biClrUsed: 0,
+        biClrImportant: 0,
+    };
+
+    let bitmap_size: usize = (((bitmap.bmWidth * 32 + 31) / 32) * 4 * bitmap.bmHeight) as usize;
+    // println!("bitmap size: {}", bitmap_size);
+    // let mut buffer: Vec<u8> = vec![0; bitmap_size];
+
+    // let h_dib = GlobalAlloc(GHND, bitmap_size);
+    // let lpbitmap = GlobalLock(h_dib);
+    // println!("bitmap {:p}", lpbitmap);
+    let mut buffer: Vec<u8> = vec![0; bitmap_size];
+
+    GetDIBits(
+        dc_window,
+        hbm,
+        0,
+        bitmap.bmHeight as u32,
+        // lpbitmap,
+        buffer.as_mut_ptr() as *mut c_void,
+        (&mut bi) as *mut BITMAPINFOHEADER as *mut BITMAPINFO,
+        DIB_RGB_COLORS,
+    );
+
+    // let buffer: Vec<u8> = Vec::from_raw_parts(lpbitmap as *mut u8, bitmap_size, bitmap_size);
+    // for i in 0..10 {
+    //     println!("{}", buffer[i]);
+    // }
+
+    // println!("{}", buffer[0]);
+
+    DeleteObject(hbm as *mut c_void);
+    DeleteObject(dc_mem as *mut c_void);
+    ReleaseDC(null_mut(), dc_window);
+
+    Ok(buffer)
+}
+
+pub fn capture_absolute(rect: &PixelRect) -> Result<Vec<u8>> {
+    unsafe { unsafe_capture(&rect) }
+}
@@ -0,0 +1,876 @@
+use anyhow::{anyhow, Result};
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use log::{debug, error, info, warn};
+use serde::{Deserialize, Serialize};
+
+use crate::artifact::internal_artifact::{
+    ArtifactSetKey, ArtifactSlotKey, ArtifactStat, ArtifactStatKey, CharacterKey, InternalArtifact,
+};
+use crate::artifact::main_stat::MainStatCheck;
+use crate::artifact::recognition_error::{ErrorSummary, RecognitionError, RecognitionErrorKind};
+use crate::artifact::{correction, main_stat};
+use crate::common::{utils, PixelRect, RawCaptureImage, RawImage};
+use crate::inference::inference::{CRNNModel, InferenceResult, ModelPaths, TextModel};
+use crate::inference::pre_process::pre_process;
+use crate::info::info::ScanInfo;
+use crate::lock::fingerprint::{ArtifactFingerprint, FingerprintStat};
+
+// the confidence of each field, see InferenceResult
+#[derive(Debug, Clone, Serialize)]
+pub struct YasScanConfidence {
+    pub name: f32,
+    pub main_stat_name: f32,
+    pub main_stat_value: f32,
+    pub sub_stat_1: f32,
+    pub sub_stat_2: f32,
+    pub sub_stat_3: f32,
+    pub sub_stat_4: f32,
+    pub level: f32,
+    pub location: f32,
+}
+
+impl YasScanConfidence {
+    pub fn min(&self) -> f32 {
+        [
+            self.name,
+            self.main_stat_name,
+            self.main_stat_value,
+            self.sub_stat_1,
+            self.sub_stat_2,
+            self.sub_stat_3,
+            self.sub_stat_4,
+            self.level,
+            self.location,
+        ]
+        .iter()
+        .cloned()
+        .fold(1.0, f32::min)
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct YasScanResult {
+    name: String,
+    main_stat_name: String,
+    main_stat_value: String,
+    sub_stat_1: String,
+    sub_stat_2: String,
+    sub_stat_3: String,
+    sub_stat_4: String,
+    level: String,
+    location: String,
+    rarity: u32,
+    lock: bool,
+    confidence: YasScanConfidence,
+}
+
+impl YasScanResult {
+    pub fn confidence(&self) -> &YasScanConfidence {
+        &self.confidence
+    }
+
+    // "+20" -> 20
+    fn parse_level(&self) -> Option<u32> {
+        if !self.level.contains("+") {
+            return None;
+        }
+        self.level
+            .chars()
+            .skip(1)
+            .collect::<String>()
+            .parse::<u32>()
+            .ok()
+    }
+
+    // A line without `+` is not a substat, e.g. the set name below the last
+    // one. A substat that can't be parsed is left out and reported, so that
+    // one bad line does not lose the whole artifact.
+    fn parse_sub_stat(
+        field: &'static str,
+        raw: &str,
+        warnings: &mut Vec<RecognitionError>,
+    ) -> Option<ArtifactStat> {
+        if !raw.contains("+") {
+            return None;
+        }
+        match ArtifactStat::from_zh_cn_raw(raw) {
+            Ok(stat) => Some(stat),
+            Err(kind) => {
+                warnings.push(RecognitionError::new(field, raw, kind));
+                None
+            }
+        }
+    }
+
+    // The fields a lock.json v3 fingerprint covers, straight from the text.
+    // Unlike to_internal_artifact, an unreadable main-stat value or location
+    // is no reason to skip a target, and the confidence is not looked at.
+    pub fn to_fingerprint(&self) -> Result<ArtifactFingerprint, RecognitionError> {
+        use RecognitionErrorKind::*;
+
+        let name = correction::correct_piece_name(&self.name);
+        let set_key = ArtifactSetKey::from_zh_cn(name)
+            .ok_or_else(|| RecognitionError::new("name", &self.name, UnknownSet))?;
+        let slot_key = ArtifactSlotKey::from_zh_cn(name)
+            .ok_or_else(|| RecognitionError::new("name", &self.name, UnknownSlot))?;
+        let level = self
+            .parse_level()
+            .ok_or_else(|| RecognitionError::new("level", &self.level, BadLevel))?;
+        let main_stat_key = ArtifactStatKey::from_zh_cn(
+            correction::correct_stat_name(&self.main_stat_name),
+            self.main_stat_value.contains("%"),
+        )
+        .ok_or_else(|| RecognitionError::new("main_stat", &self.main_stat_name, StatParse))?;
+
+        let mut substats = Vec::new();
+        let raw_substats = [
+            ("sub_stat_1", &self.sub_stat_1),
+            ("sub_stat_2", &self.sub_stat_2),
+            ("sub_stat_3", &self.sub_stat_3),
+            ("sub_stat_4", &self.sub_stat_4),
+        ];
+        for (field, raw) in raw_substats.iter() {
+            if !raw.contains("+") {
+                continue;
+            }
+            let stat = ArtifactStat::from_zh_cn_raw(raw)
+                .map_err(|kind| RecognitionError::new(field, raw, kind))?;
+            substats.push(FingerprintStat {
+                key: stat.key.to_good(),
+                value: stat.value,
+            });
+        }
+
+        Ok(ArtifactFingerprint {
+            set_key: set_key.to_good(),
+            slot_key: slot_key.to_good(),
+            rarity: self.rarity,
+            level,
+            main_stat_key: main_stat_key.to_good(),
+            substats,
+        })
+    }
+
+    pub fn to_internal_artifact(&self) -> Result<InternalArtifact, RecognitionError> {
+        self.to_internal_artifact_with_warnings()
+            .map(|(artifact, _)| artifact)
+    }
+
+    // Like to_internal_artifact, also returning the problems that did not
+    // stop recognition: substats that can't be parsed are left out, unknown
+    // characters give no location and main-stat values that do not fit
+    // their level are kept.
+    pub fn to_internal_artifact_with_warnings(
+        &self,
+    ) -> Result<(InternalArtifact, Vec<RecognitionError>), RecognitionError> {
+        use RecognitionErrorKind::*;
+
+        let mut warnings = Vec::new();
+
+        let name = correction::correct_piece_name(&self.name);
+        let set_key = ArtifactSetKey::from_zh_cn(name)
+            .ok_or_else(|| RecognitionError::new("name", &self.name, UnknownSet))?;
+        let slot_key = ArtifactSlotKey::from_zh_cn(name)
+            .ok_or_else(|| RecognitionError::new("name", &self.name, UnknownSlot))?;
+        let rarity = self.rarity;
+        let level = self
+            .parse_level()
+            .ok_or_else(|| RecognitionError::new("level", &self.level, BadLevel))?;
+        let main_stat_raw = self.main_stat_name.replace("+", "?") + "+" + &self.main_stat_value;
+        let mut main_stat = ArtifactStat::from_zh_cn_raw(&main_stat_raw)
+            .map_err(|kind| RecognitionError::new("main_stat", &main_stat_raw, kind))?;
+        match main_stat::validate(&main_stat, &self.main_stat_value, rarity, level) {
+            MainStatCheck::Match => (),
+            MainStatCheck::Corrected(value) => {
+                debug!("main stat `{}` corrected to {}", main_stat_raw, value);
+                main_stat.value = value;
+                warnings.push(RecognitionError::new(
+                    "main_stat",
+                    &main_stat_raw,
+                    MainStatCorrected,
+                ));
+            }
+            MainStatCheck::Mismatch(mismatch) => {
+                debug!("{}", mismatch);
+                warnings.push(RecognitionError::new(
+                    "main_stat",
+                    &main_stat_raw,
+                    MainStatMismatch,
+                ));
+            }
+        }
+        let sub1 = Self::parse_sub_stat("sub_stat_1", &self.sub_stat_1, &mut warnings);
+        let sub2 = Self::parse_sub_stat("sub_stat_2", &self.sub_stat_2, &mut warnings);
+        let sub3 = Self::parse_sub_stat("sub_stat_3", &self.sub_stat_3, &mut warnings);
+        let sub4 = Self::parse_sub_stat("sub_stat_4", &self.sub_stat_4, &mut warnings);
+
+        let location = if self.location.contains("已装备") {
+            let len = self.location.chars().count();
+            let name = self.location.chars().take(len - 3).collect::<String>();
+            let character = CharacterKey::from_zh_cn(&name);
+            if character.is_none() {
+                warnings.push(RecognitionError::new(
+                    "location",
+                    &self.location,
+                    UnknownCharacter,
+                ));
+            }
+            character
+        } else {
+            None
+        };
+
+        let art = InternalArtifact {
+            set_key,
+            slot_key,
+            rarity,
+            level,
+            location,
+            lock: self.lock,
+            main_stat,
+            sub_stat_1: sub1,
+            sub_stat_2: sub2,
+            sub_stat_3: sub3,
+            sub_stat_4: sub4,
+        };
+        Ok((art, warnings))
+    }
+}
+
+// how many artifacts are recognized with one model run at most
+pub const BATCH_SIZE: usize = 4;
+
+// One slot of the backpack. `index` is its position in scan order, which is
+// what lock.json refers to, so failed recognitions are kept as Unrecognized
+// and artifacts below --min-level as Skipped instead of being dropped.
+#[derive(Debug)]
+pub enum ArtifactRecord {
+    Recognized {
+        index: usize,
+        artifact: InternalArtifact,
+    },
+    Unrecognized {
+        index: usize,
+        raw: Box<YasScanResult>,
+        error: RecognitionError,
+    },
+    Skipped {
+        index: usize,
+    },
+}
+
+impl ArtifactRecord {
+    pub fn index(&self) -> usize {
+        match self {
+            ArtifactRecord::Recognized { index, .. } => *index,
+            ArtifactRecord::Unrecognized { index, .. } => *index,
+            ArtifactRecord::Skipped { index } => *index,
+        }
+    }
+
+    pub fn artifact(&self) -> Option<&InternalArtifact> {
+        match self {
+            ArtifactRecord::Recognized { artifact, .. } => Some(artifact),
+            ArtifactRecord::Unrecognized { .. } | ArtifactRecord::Skipped { .. } => None,
+        }
+    }
+}
+
+pub fn recognized_artifacts(records: &[ArtifactRecord]) -> Vec<InternalArtifact> {
+    records
+        .iter()
+        .filter_map(|r| r.artifact())
+        .cloned()
+        .collect()
+}
+
+pub fn recognized_indices(records: &[ArtifactRecord]) -> Vec<usize> {
+    records
+        .iter()
+        .filter(|r| r.artifact().is_some())
+        .map(|r| r.index())
+        .collect()
+}
+
+pub fn unrecognized_indices(records: &[ArtifactRecord]) -> Vec<u32> {
+    records
+        .iter()
+        .filter(|r| matches!(r, ArtifactRecord::Unrecognized { .. }))
+        .map(|r| r.index() as u32)
+        .collect()
+}
+
+// scan_summary.json, the number of slots scanned, which the exports can't
+// tell when trailing ones were left out by --min-level
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ScanSummary {
+    pub art_count: u32,
+}
+
+impl ScanSummary {
+    pub fn new(records: &[ArtifactRecord]) -> ScanSummary {
+        ScanSummary {
+            art_count: records.len() as u32,
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct UnrecognizedRecord<'a> {
+    index: usize,
+    error: &'a RecognitionError,
+    raw: &'a YasScanResult,
+}
+
+// unrecognized.json, so that the slots can be checked by hand
+pub fn dump_unrecognized(records: &[ArtifactRecord], path: PathBuf) -> Result<()> {
+    let unrecognized: Vec<UnrecognizedRecord> = records
+        .iter()
+        .filter_map(|r| match r {
+            ArtifactRecord::Unrecognized { index, raw, error } => Some(UnrecognizedRecord {
+                index: *index,
+                error,
+                raw,
+            }),
+            _ => None,
+        })
+        .collect();
+    utils::dump_json(&unrecognized, path)
+}
+
+// The recognition half of a scan: turns panel screenshots (plus the star and
+// lock read from the game) into artifacts. Fed by YasScanner while scanning,
+// or by recognize_dir from panels saved earlier.
+pub struct ArtifactRecognizer {
+    model: Box<dyn TextModel>,
+    info: ScanInfo,
+    dump_mode: bool,
+    min_confidence: f32,
+
+    results: Vec<ArtifactRecord>,
+    // every raw result, written to dumps/debug.json in dump mode
+    records: Vec<YasScanResult>,
+    hash: HashSet<InternalArtifact>,
+    errors: ErrorSummary,
+    // problems of artifacts that were still recognized
+    warnings: ErrorSummary,
+    dup_count: u32,
+    consecutive_dup_count: u32,
+    cnt: i32,
+}
+
+impl ArtifactRecognizer {
+    pub fn new(
+        info: ScanInfo,
+        model_paths: &ModelPaths,
+        dump_mode: bool,
+        min_confidence: f32,
+    ) -> Result<ArtifactRecognizer> {
+        let model = Box::new(CRNNModel::load(model_paths)?);
+        Self::with_model(info, model, dump_mode, min_confidence)
+    }
+
+    pub fn with_model(
+        info: ScanInfo,
+        model: Box<dyn TextModel>,
+        dump_mode: bool,
+        min_confidence: f32,
+    ) -> Result<ArtifactRecognizer> {
+        if dump_mode {
+            fs::create_dir_all("dumps")?;
+        }
+
+        Ok(ArtifactRecognizer {
+            model,
+            info,
+            dump_mode,
+            min_confidence,
+
+            results: Vec::new(),
+            records: Vec::new(),
+            hash: HashSet::new(),
+            errors: ErrorSummary::default(),
+            warnings: ErrorSummary::default(),
+            dup_count: 0,
+            consecutive_dup_count: 0,
+            cnt: 0,
+        })
+    }
+
+    // crop a field and pre-process it for the model, None if it is blank
+    fn prepare(
+        &self,
+        capture: &RawCaptureImage,
+        pos: &PixelRect,
+        name: &str,
+        cnt: i32,
+    ) -> Result<Option<RawImage>> {
+        let raw_img = capture.crop_to_raw_img(pos);
+        if self.dump_mode {
+            raw_img
+                .grayscale_to_gray_image()
+                .save(format!("dumps/{}_{}.png", name, cnt))?;
+        }
+
+        let processed_img = pre_process(raw_img);
+
+        if processed_img.w == 0 || processed_img.h == 0 {
+            return Ok(None);
+        }
+
+        if self.dump_mode {
+            processed_img
+                .to_gray_image()
+                .save(format!("dumps/p_{}_{}.png", name, cnt))?;
+        }
+        Ok(Some(processed_img))
+    }
+
+    // recognize the panels of several artifacts with a single model run
+    pub fn recognize_batch(
+        &mut self,
+        panels: &[(RawCaptureImage, u32, bool)],
+    ) -> Result<Vec<YasScanResult>> {
+        let info = &self.info;
+        let fields = [
+            (&info.title_position, "title"),
+            (&info.main_stat_name_position, "main_stat_name"),
+            (&info.main_stat_value_position, "main_stat_value"),
+            (&info.sub_stat1_position, "sub_stat_1"),
+            (&info.sub_stat2_position, "sub_stat_2"),
+            (&info.sub_stat3_position, "sub_stat_3"),
+            (&info.sub_stat4_position, "sub_stat_4"),
+            (&info.level_position, "level"),
+            (&info.equip_position, "equip"),
+        ];
+
+        let mut imgs: Vec<Option<RawImage>> = Vec::new();
+        for (i, (capture, _, _)) in panels.iter().enumerate() {
+            for (pos, name) in fields.iter() {
+                imgs.push(self.prepare(capture, pos, name, self.cnt + i as i32)?);
+            }
+        }
+
+        let batch: Vec<&RawImage> = imgs.iter().filter_map(|im| im.as_ref()).collect();
+        let mut inferred = self.model.inference_batch(&batch)?.into_iter();
+
+        let mut results = Vec::new();
+        for (i, (_, rarity, lock)) in panels.iter().enumerate() {
+            let mut texts: Vec<InferenceResult> = Vec::new();
+            for (j, (_, name)) in fields.iter().enumerate() {
+                let r = match imgs[i * fields.len() + j] {
+                    Some(_) => inferred
+                        .next()
+                        .ok_or_else(|| anyhow!("missing inference result"))?,
+                    None => InferenceResult::empty(),
+                };
+                if self.dump_mode {
+                    fs::write(format!("dumps/{}_{}.txt", name, self.cnt), &r.text)?;
+                }
+                texts.push(r);
+            }
+
+            // in the order of `fields`
+            let mut texts = texts.into_iter();
+            let mut next = || texts.next().unwrap_or_else(InferenceResult::empty);
+            let title = next();
+            let main_stat_name = next();
+            let main_stat_value = next();
+            let sub_stat_1 = next();
+            let sub_stat_2 = next();
+            let sub_stat_3 = next();
+            let sub_stat_4 = next();
+            let level = next();
+            let equip = next();
+
+            let result = YasScanResult {
+                confidence: YasScanConfidence {
+                    name: title.confidence,
+                    main_stat_name: main_stat_name.confidence,
+                    main_stat_value: main_stat_value.confidence,
+                    sub_stat_1: sub_stat_1.confidence,
+                    sub_stat_2: sub_stat_2.confidence,
+                    sub_stat_3: sub_stat_3.confidence,
+                    sub_stat_4: sub_stat_4.confidence,
+                    level: level.confidence,
+                    location: equip.confidence,
+                },
+                name: title.text,
+                main_stat_name: main_stat_name.text,
+                main_stat_value: main_stat_value.text,
+                sub_stat_1: sub_stat_1.text,
+                sub_stat_2: sub_stat_2.text,
+                sub_stat_3: sub_stat_3.text,
+                sub_stat_4: sub_stat_4.text,
+                level: level.text,
+                location: equip.text,
+                rarity: *rarity,
+                lock: *lock,
+            };
+            self.cnt += 1;
+
+            debug!("{:?}", result);
+            results.push(result);
+        }
+        Ok(results)
+    }
+
+    // recognize panels and collect the artifacts, returns false when the
+    // scan should stop
+    pub fn push_batch(&mut self, panels: &[(RawCaptureImage, u32, bool)]) -> Result<bool> {
+        for result in self.recognize_batch(panels)? {
+            if !self.collect(result) {
+                return Ok(false);
+            }
+        }
+        Ok(true)
+    }
+
+    fn collect(&mut self, result: YasScanResult) -> bool {
+        let index = self.results.len();
+
+        let confidence = result.confidence.min();
+        if confidence < self.min_confidence {
+            warn!(
+                "低置信度({:.3})，请手动检查第{}个圣遗物: {:?}",
+                confidence,
+                index + 1,
+                result
+            );
+        }
+        if self.dump_mode {
+            self.records.push(result.clone());
+        }
+
+        match result.to_internal_artifact_with_warnings() {
+            Ok((artifact, warnings)) => {
+                for warning in warnings.iter() {
+                    warn!("第{}个圣遗物: {}", index + 1, warning);
+                    self.warnings.add(index, warning);
+                }
+                if self.hash.contains(&artifact) {
+                    self.dup_count += 1;
+                    self.consecutive_dup_count += 1;
+                    warn!("dup artifact detected: {:?}", result);
+                } else {
+                    self.consecutive_dup_count = 0;
+                    self.hash.insert(artifact.clone());
+                }
+                if matches!(artifact.analyze_rolls(), Some(r) if !r.consistent) {
+                    warn!(
+                        "副词条数值不可能由强化得到，请手动检查第{}个圣遗物: {:?}",
+                        index + 1,
+                        result
+                    );
+                }
+                self.results
+                    .push(ArtifactRecord::Recognized { index, artifact });
+            }
+            Err(error) => {
+                error!("wrong detection: {}, {:?}", error, result);
+                self.errors.add(index, &error);
+                self.results.push(ArtifactRecord::Unrecognized {
+                    index,
+                    raw: Box::new(result),
+                    error,
+                });
+            }
+        }
+
+        if self.consecutive_dup_count >= self.info.art_row {
+            error!("检测到连续多个重复圣遗物，可能为翻页错误，或者为非背包顶部开始扫描");
+            return false;
+        }
+        true
+    }
+
+    pub fn finish(self, min_level: u32) -> Result<Vec<ArtifactRecord>> {
+        info!("error count: {}", self.errors.total());
+        self.errors.log();
+        info!("warning count: {}", self.warnings.total());
+        self.warnings.log();
+        info!("dup count: {}", self.dup_count);
+
+        if self.dump_mode {
+            let records: Vec<DebugRecord> = self
+                .records
+                .iter()
+                .enumerate()
+                .map(|(index, result)| DebugRecord {
+                    index,
+                    min_confidence: result.confidence.min(),
+                    low_confidence: result.confidence.min() < self.min_confidence,
+                    result,
+                })
+                .collect();
+            utils::dump_json(&records, PathBuf::from("dumps/debug.json"))?;
+            utils::dump_json(&self.errors, PathBuf::from("dumps/errors.json"))?;
+            utils::dump_json(&self.warnings, PathBuf::from("dumps/warnings.json"))?;
+        }
+
+        // unrecognized records are kept, their level is unknown
+        Ok(self
+            .results
+            .into_iter()
+            .map(|r| match r.artifact() {
+                Some(a) if a.level < min_level => ArtifactRecord::Skipped { index: r.index() },
+                _ => r,
+            })
+            .collect())
+    }
+}
+
+#[derive(Serialize)]
+struct DebugRecord<'a> {
+    index: usize,
+    min_confidence: f32,
+    low_confidence: bool,
+    #[serde(flatten)]
+    result: &'a YasScanResult,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct SavedPanel {
+    pub file: String,
+    pub rarity: u32,
+    pub lock: bool,
+}
+
+// Content of `panels.json`. The window size is enough to rebuild the layout,
+// since the panel positions are relative to the window.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct SavedPanels {
+    pub width: u32,
+    pub height: u32,
+    pub panels: Vec<SavedPanel>,
+}
+
+// Saves the panel screenshots of a scan, so that recognition can be run
+// again with recognize_dir.
+pub struct PanelSaver {
+    dir: PathBuf,
+    index: SavedPanels,
+}
+
+impl PanelSaver {
+    pub fn new(dir: &Path, info: &ScanInfo) -> Result<PanelSaver> {
+        fs::create_dir_all(dir)?;
+        Ok(PanelSaver {
+            dir: dir.to_path_buf(),
+            index: SavedPanels {
+                width: info.width,
+                height: info.height,
+                panels: Vec::new(),
+            },
+        })
+    }
+
+    pub fn save(&mut self, capture: &RawCaptureImage, rarity: u32, lock: bool) -> Result<()> {
+        let file = format!("{:04}.png", self.index.panels.len());
+        let path = self.dir.join(&file);
+        capture.save(path.to_str().ok_or_else(|| anyhow!("invalid panel path"))?)?;
+        self.index.panels.push(SavedPanel { file, rarity, lock });
+        Ok(())
+    }
+
+    pub fn finish(self) -> Result<()> {
+        let path = self.dir.join("panels.json");
+        fs::write(&path, serde_json::to_string_pretty(&self.index)?)?;
+        info!(
+            "saved {} panels to {}",
+            self.index.panels.len(),
+            self.dir.display()
+        );
+        Ok(())
+    }
+}
+
+pub fn recognize_dir(
+    dir: &Path,
+    model_paths: &ModelPaths,
+    dump_mode: bool,
+    min_level: u32,
+    min_confidence: f32,
+) -> Result<Vec<ArtifactRecord>> {
+    let model = Box::new(CRNNModel::load(model_paths)?);
+    recognize_dir_with_model(dir, model, dump_mode, min_level, min_confidence)
+}
+
+pub fn recognize_dir_with_model(
+    dir: &Path,
+    model: Box<dyn TextModel>,
+    dump_mode: bool,
+    min_level: u32,
+    min_confidence: f32,
+) -> Result<Vec<ArtifactRecord>> {
+    let path = dir.join("panels.json");
+    let json_str =
+        fs::read_to_string(&path).map_err(|_| anyhow!("cannot read {}", path.display()))?;
+    let saved: SavedPanels = serde_json::from_str(&json_str)?;
+
+    let info = ScanInfo::from_rect(&PixelRect {
+        left: 0,
+        top: 0,
+        width: saved.width as i32,
+        height: saved.height as i32,
+    })
+    .map_err(|e| anyhow!(e))?;
+
+    let mut recognizer = ArtifactRecognizer::with_model(info, model, dump_mode, min_confidence)?;
+    for chunk in saved.panels.chunks(BATCH_SIZE) {
+        let mut batch = Vec::new();
+        for panel in chunk {
+            let capture = RawCaptureImage::load(dir.join(&panel.file))?;
+            batch.push((capture, panel.rarity, panel.lock));
+        }
+        if !recognizer.push_batch(&batch)? {
+            break;
+        }
+    }
+
+    let results = recognizer.finish(min_level)?;
+    info!("count: {}", results.len());
+    Ok(results)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::simulator::Simulator;
+
+    fn result(sub_stat_1: &str, location: &str) -> YasScanResult {
+        YasScanResult {
+            name: String::from("角斗士的留恋"),
+            main_stat_name: String::from("生命值"),
+            main_stat_value: String::from("4,780"),
+            sub_stat_1: String::from(sub_stat_1),
+            sub_stat_2: String::from("暴击率+3.9%"),
+            sub_stat_3: String::new(),
+            sub_stat_4: String::new(),
+            level: String::from("+20"),
+            location: String::from(location),
+            rarity: 5,
+            lock: true,
+            confidence: YasScanConfidence {
+                name: 1.0,
+                main_stat_name: 1.0,
+                main_stat_value: 1.0,
+                sub_stat_1: 1.0,
+                sub_stat_2: 1.0,
+                sub_stat_3: 1.0,
+                sub_stat_4: 1.0,
+                level: 1.0,
+                location: 1.0,
+            },
+        }
+    }
+
+    #[test]
+    fn recognized() {
+        let (artifact, warnings) = result("暴击伤害+7.8%", "")
+            .to_internal_artifact_with_warnings()
+            .unwrap();
+        assert!(warnings.is_empty());
+        assert_eq!(artifact.main_stat.value, 4780.0);
+        assert_eq!(artifact.sub_stats().len(), 2);
+        assert_eq!(artifact.location, None);
+    }
+
+    #[test]
+    fn unknown_character_and_bad_substat_are_not_fatal() {
+        let (artifact, warnings) = result("暴击伤害+7.X%", "某个新角色已装备")
+            .to_internal_artifact_with_warnings()
+            .unwrap();
+        assert_eq!(artifact.location, None);
+        assert_eq!(artifact.sub_stat_1, None);
+        assert_eq!(
+            artifact.sub_stat_2.map(|s| s.key),
+            Some(ArtifactStatKey::Critical)
+        );
+        let kinds: Vec<RecognitionErrorKind> = warnings.iter().map(|w| w.kind).collect();
+        assert_eq!(
+            kinds,
+            vec![
+                RecognitionErrorKind::StatParse,
+                RecognitionErrorKind::UnknownCharacter
+            ]
+        );
+    }
+
+    #[test]
+    fn unknown_piece_is_fatal() {
+        let mut r = result("暴击伤害+7.8%", "");
+        r.name = String::from("不存在的圣遗物");
+        assert_eq!(
+            r.to_internal_artifact().unwrap_err().kind,
+            RecognitionErrorKind::UnknownSet
+        );
+    }
+
+    #[test]
+    fn fingerprint_ignores_location_and_confidence() {
+        let mut r = result("暴击伤害+7.8%", "某个新角色已装备");
+        r.main_stat_value = String::from("4,7B0");
+        r.confidence.location = 0.1;
+        let (artifact, _) = result("暴击伤害+7.8%", "")
+            .to_internal_artifact_with_warnings()
+            .unwrap();
+        let expected = ArtifactFingerprint::from_artifact(&artifact);
+        assert_eq!(expected.diff(&r.to_fingerprint().unwrap()), None);
+
+        r.sub_stat_1 = String::from("暴击伤害+7.2%");
+        assert!(expected.diff(&r.to_fingerprint().unwrap()).is_some());
+    }
+
+    #[test]
+    fn saved_panels_are_recognized_again() {
+        let artifacts: Vec<InternalArtifact> =
+            [("暴击伤害+7.8%", ""), ("攻击力+19", "班尼特已装备")]
+                .iter()
+                .map(|(sub, location)| result(sub, location).to_internal_artifact().unwrap())
+                .collect();
+        let info = ScanInfo::from_16_9(1280, 720, 0, 0);
+        let sim = Simulator::new(artifacts.clone(), info.clone());
+        let (mut screen, mut input) = (sim.screen(), sim.input());
+
+        let dir = std::env::temp_dir().join(format!("yas_panels_{}", std::process::id()));
+        let mut saver = PanelSaver::new(&dir, &info).unwrap();
+        for (i, artifact) in artifacts.iter().enumerate() {
+            input.move_to(
+                (info.left_margin + info.art_width as f64 / 2.0 + info.art_shift_x * i as f64)
+                    as i32,
+                (info.top_margin + info.art_height as f64 / 2.0) as i32,
+            );
+            input.click();
+            let capture = screen.capture(&info.panel_position).unwrap();
+            saver
+                .save(&capture, artifact.rarity, artifact.lock)
+                .unwrap();
+        }
+        saver.finish().unwrap();
+
+        let saved: SavedPanels =
+            serde_json::from_str(&fs::read_to_string(dir.join("panels.json")).unwrap()).unwrap();
+        assert_eq!((saved.width, saved.height), (1280, 720));
+        let files: Vec<&str> = saved.panels.iter().map(|p| p.file.as_str()).collect();
+        assert_eq!(files, vec!["0000.png", "0001.png"]);
+
+        let model = (sim.model_loader())().unwrap();
+        let records = recognize_dir_with_model(&dir, model, false, 0, 0.0).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(recognized_indices(&records), vec![0, 1]);
+        assert_eq!(recognized_artifacts(&records), artifacts);
+    }
+
+    #[test]
+    fn recognize_dir_needs_panels_json() {
+        let dir = std::env::temp_dir().join(format!("yas_no_panels_{}", std::process::id()));
+        let sim = Simulator::new(Vec::new(), ScanInfo::from_16_9(1280, 720, 0, 0));
+        let model = (sim.model_loader())().unwrap();
+        let err = recognize_dir_with_model(&dir, model, false, 0, 0.0).unwrap_err();
+        assert!(err.to_string().contains("panels.json"));
+    }
+}
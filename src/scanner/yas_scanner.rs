@@ -1,30 +1,35 @@
 use anyhow::{anyhow, Result};
 use std::cmp::min;
-use std::collections::HashSet;
 use std::convert::From;
 use std::fs;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use std::sync::mpsc;
 use std::thread;
 use std::time::SystemTime;
 // use tract_onnx::tract_core::downcast_rs::Downcast;
 
 use clap::ArgMatches;
-use dxgcap::DXGIManager;
-use enigo::*;
-use log::{debug, error, info, trace, warn};
+use log::{debug, info, trace, warn};
 use serde::{Deserialize, Serialize};
 
-use crate::artifact::internal_artifact::{
-    ArtifactSetKey, ArtifactSlotKey, ArtifactStat, ArtifactStatKey, CharacterKey, InternalArtifact,
-};
-use crate::capture::{self, capture_absolute_raw_image};
+#[cfg(windows)]
+use crate::capture::screen_source::{DxgiSource, GdiSource};
+use crate::capture::screen_source::{FileSource, RecordingSource, ScreenSource};
 use crate::common::color::Color;
-use crate::common::{utils, PixelRect, RawCaptureImage};
-use crate::inference::inference::CRNNModel;
+use crate::common::{utils, PixelRect, PixelRectBound, RawCaptureImage, RawImage};
+use crate::inference::inference::{CRNNModel, ModelLoader, ModelPaths, TextModel};
 use crate::inference::pre_process::pre_process;
 use crate::info::info::ScanInfo;
-use crate::lock::{LockAction, LockActionType};
+#[cfg(windows)]
+use crate::input::EnigoDriver;
+use crate::input::InputDriver;
+#[cfg(not(windows))]
+use crate::input::RecordingDriver;
+use crate::lock::journal::LockJournal;
+use crate::lock::{LockAction, LockActionType, LockReport};
+use crate::scanner::artifact_recognizer::{
+    ArtifactRecognizer, ArtifactRecord, PanelSaver, BATCH_SIZE,
+};
 
 #[derive(Debug, Default, Deserialize, Serialize)]
 pub struct YasScannerConfig {
@@ -46,6 +51,17 @@ pub struct YasScannerConfig {
     scroll_speed: f64,
     lock_stop: u32,
     max_wait_lock: u32,
+    // how long lock() waits for a flip that did not show before clicking
+    // again, independent of max_wait_lock, checking every flip_check_stop ms
+    max_wait_late_flip: u32,
+    flip_check_stop: u32,
+    lock_retry: u32,
+    record: Option<String>,
+    replay: Option<String>,
+    save_panels: Option<String>,
+    min_confidence: f32,
+    model: Option<String>,
+    vocab: Option<String>,
 }
 
 impl YasScannerConfig {
@@ -69,8 +85,24 @@ impl YasScannerConfig {
             scroll_speed: *matches.get_one("scroll-speed").unwrap(),
             lock_stop: *matches.get_one("lock-stop").unwrap(),
             max_wait_lock: *matches.get_one("max-wait-lock").unwrap(),
+            max_wait_late_flip: *matches.get_one("max-wait-late-flip").unwrap(),
+            flip_check_stop: *matches.get_one("flip-check-stop").unwrap(),
+            lock_retry: *matches.get_one("lock-retry").unwrap(),
+            record: matches.get_one::<String>("record").cloned(),
+            replay: matches.get_one::<String>("replay").cloned(),
+            save_panels: matches.get_one::<String>("save-panels").cloned(),
+            min_confidence: *matches.get_one("min-confidence").unwrap(),
+            model: matches.get_one::<String>("model").cloned(),
+            vocab: matches.get_one::<String>("vocab").cloned(),
         })
     }
+
+    pub fn model_paths(&self) -> ModelPaths {
+        ModelPaths {
+            model: self.model.as_ref().map(PathBuf::from),
+            vocab: self.vocab.as_ref().map(PathBuf::from),
+        }
+    }
 }
 
 #[allow(dead_code)]
@@ -81,68 +113,6 @@ enum ScrollResult {
     Skip,
 }
 
-#[derive(Debug)]
-pub struct YasScanResult {
-    name: String,
-    main_stat_name: String,
-    main_stat_value: String,
-    sub_stat_1: String,
-    sub_stat_2: String,
-    sub_stat_3: String,
-    sub_stat_4: String,
-    level: String,
-    location: String,
-    rarity: u32,
-    lock: bool,
-}
-
-impl YasScanResult {
-    pub fn to_internal_artifact(&self) -> Option<InternalArtifact> {
-        let set_key = ArtifactSetKey::from_zh_cn(&self.name)?;
-        let slot_key = ArtifactSlotKey::from_zh_cn(&self.name)?;
-        let rarity = self.rarity;
-        if !self.level.contains("+") {
-            return None;
-        }
-        let level = self
-            .level
-            .chars()
-            .skip(1)
-            .collect::<String>()
-            .parse::<u32>()
-            .ok()?;
-        let main_stat = ArtifactStat::from_zh_cn_raw(
-            (self.main_stat_name.replace("+", "?") + "+" + self.main_stat_value.as_str()).as_str(),
-        )?;
-        let sub1 = ArtifactStat::from_zh_cn_raw(&self.sub_stat_1);
-        let sub2 = ArtifactStat::from_zh_cn_raw(&self.sub_stat_2);
-        let sub3 = ArtifactStat::from_zh_cn_raw(&self.sub_stat_3);
-        let sub4 = ArtifactStat::from_zh_cn_raw(&self.sub_stat_4);
-
-        let location = if self.location.contains("已装备") {
-            let len = self.location.chars().count();
-            CharacterKey::from_zh_cn(&self.location.chars().take(len - 3).collect::<String>())
-        } else {
-            None
-        };
-
-        let art = InternalArtifact {
-            set_key,
-            slot_key,
-            rarity,
-            level,
-            location,
-            lock: self.lock,
-            main_stat,
-            sub_stat_1: sub1,
-            sub_stat_2: sub2,
-            sub_stat_3: sub3,
-            sub_stat_4: sub4,
-        };
-        Some(art)
-    }
-}
-
 fn eq(x: u8, y: u8, threshold: u8) -> bool {
     if x < y {
         y - x <= threshold
@@ -166,9 +136,11 @@ fn get_pool_of_rect(shot: &RawCaptureImage, rect: &PixelRect) -> Result<f64> {
 }
 
 pub struct YasScanner {
-    model: CRNNModel,
-    enigo: Enigo,
-    dxg: Option<DXGIManager>,
+    model: Box<dyn TextModel>,
+    // more models for the recognition thread and lock
+    model_loader: ModelLoader,
+    source: Box<dyn ScreenSource>,
+    input: Box<dyn InputDriver>,
 
     info: ScanInfo,
     config: YasScannerConfig,
@@ -190,23 +162,76 @@ pub struct YasScanner {
 
 impl YasScanner {
     pub fn new(info: ScanInfo, config: YasScannerConfig) -> Result<YasScanner> {
-        let row = info.art_row;
-        let col = info.art_col;
+        let mut source = Self::default_source(&config)?;
+        if let Some(dir) = &config.record {
+            let window = PixelRect {
+                left: info.left,
+                top: info.top,
+                width: info.width as i32,
+                height: info.height as i32,
+            };
+            source = Box::new(RecordingSource::new(source, Path::new(dir), &window)?);
+        }
 
-        let mut dxg = None;
-        if config.dxgcap {
-            dxg = Some(DXGIManager::new(1000).map_err(|s| anyhow!(s))?);
-            // dxg的第一张截图可能是黑屏
-            dxg.as_mut()
-                .unwrap()
-                .capture_frame()
-                .map_err(|e| anyhow!("dxg capture init err: {:?}", e))?;
+        let input = Self::default_input();
+        Self::with_devices(info, config, source, input)
+    }
+
+    #[cfg(windows)]
+    fn default_source(config: &YasScannerConfig) -> Result<Box<dyn ScreenSource>> {
+        Ok(match &config.replay {
+            Some(dir) => Box::new(FileSource::new(Path::new(dir))?),
+            None if config.dxgcap => Box::new(DxgiSource::new()?),
+            None => Box::new(GdiSource::new()),
+        })
+    }
+
+    // only replays work without a real screen
+    #[cfg(not(windows))]
+    fn default_source(config: &YasScannerConfig) -> Result<Box<dyn ScreenSource>> {
+        match &config.replay {
+            Some(dir) => Ok(Box::new(FileSource::new(Path::new(dir))?)),
+            None => Err(anyhow!(
+                "screen capture is only supported on Windows, use --replay"
+            )),
         }
+    }
+
+    #[cfg(windows)]
+    fn default_input() -> Box<dyn InputDriver> {
+        Box::new(EnigoDriver::new())
+    }
+
+    #[cfg(not(windows))]
+    fn default_input() -> Box<dyn InputDriver> {
+        Box::new(RecordingDriver::new())
+    }
+
+    pub fn with_devices(
+        info: ScanInfo,
+        config: YasScannerConfig,
+        source: Box<dyn ScreenSource>,
+        input: Box<dyn InputDriver>,
+    ) -> Result<YasScanner> {
+        let loader = CRNNModel::loader(config.model_paths());
+        Self::with_model_loader(info, config, source, input, loader)
+    }
+
+    pub fn with_model_loader(
+        info: ScanInfo,
+        config: YasScannerConfig,
+        source: Box<dyn ScreenSource>,
+        input: Box<dyn InputDriver>,
+        model_loader: ModelLoader,
+    ) -> Result<YasScanner> {
+        let row = info.art_row;
+        let col = info.art_col;
 
         Ok(YasScanner {
-            model: CRNNModel::new()?,
-            enigo: Enigo::new(),
-            dxg,
+            model: model_loader()?,
+            model_loader,
+            source,
+            input,
 
             info,
             config,
@@ -230,41 +255,30 @@ impl YasScanner {
     // fn align_panel(&mut self) {
     //     let left: i32 = self.info.left + self.info.lock_x as i32;
     //     let top: i32 = self.info.top + self.info.lock_y as i32;
-    //     self.enigo.mouse_move_to(left, top);
+    //     self.input.move_to(left, top);
     //     self.scroll(10);
     // }
 
     fn capture(&mut self, rect: &PixelRect) -> Result<RawCaptureImage> {
-        if self.config.dxgcap {
-            let (pixels, (w, _)) = self
-                .dxg
-                .as_mut()
-                .unwrap()
-                .capture_frame()
-                .map_err(|e| anyhow!("dxg capture err: {:?}", e))?;
-
-            let mut im = RawCaptureImage {
-                data: vec![0; (rect.width * rect.height * 4) as usize],
-                w: rect.width as u32,
-                h: rect.height as u32,
-            };
-
-            for x in rect.left..rect.left + rect.width {
-                for y in rect.top..rect.top + rect.height {
-                    let p = (y * w as i32 + x) as usize;
-                    let pos = ((rect.height - 1 - (y - rect.top)) * rect.width + (x - rect.left))
-                        as usize
-                        * 4;
-                    im.data[pos + 0] = pixels[p].b;
-                    im.data[pos + 1] = pixels[p].g;
-                    im.data[pos + 2] = pixels[p].r;
-                    im.data[pos + 3] = pixels[p].a;
-                }
-            }
-            return Ok(im);
-        }
+        self.source.capture(rect)
+    }
 
-        capture_absolute_raw_image(&rect)
+    // capture a rect relative to the game window and preprocess it for the model
+    fn capture_relative(&mut self, bound: &PixelRectBound) -> Result<RawImage> {
+        let rect = PixelRect {
+            left: bound.left + self.info.left,
+            top: bound.top + self.info.top,
+            width: bound.right - bound.left,
+            height: bound.bottom - bound.top,
+        };
+        let shot = self.capture(&rect)?;
+        let raw_img = shot.crop_to_raw_img(&PixelRect {
+            left: 0,
+            top: 0,
+            width: rect.width,
+            height: rect.height,
+        });
+        Ok(pre_process(raw_img))
     }
 
     fn move_to(&mut self, row: u32, col: u32) {
@@ -277,26 +291,30 @@ impl YasScanner {
             + info.top_margin as i32
             + info.art_height as i32 / 2
             + (info.art_shift_y * row as f64) as i32;
-        self.enigo.mouse_move_to(left, top);
+        self.input.move_to(left, top);
     }
 
     fn scroll(&mut self, offset: i32) {
         if offset < 0 {
             for _ in 0..(-offset) {
-                self.enigo.mouse_scroll_y(-1);
+                self.input.scroll(-1);
             }
         } else {
             for _ in 0..offset {
-                self.enigo.mouse_scroll_y(1);
+                self.input.scroll(1);
             }
         }
         utils::sleep(self.config.scroll_stop);
     }
 
-    fn get_color(&self, x: u32, y: u32) -> Result<Color> {
-        let x = x as i32 + self.info.left;
-        let y = y as i32 + self.info.top;
-        let color = capture::get_color(x as u32, y as u32)?;
+    fn get_color(&mut self, x: u32, y: u32) -> Result<Color> {
+        let rect = PixelRect {
+            left: x as i32 + self.info.left,
+            top: y as i32 + self.info.top,
+            width: 1,
+            height: 1,
+        };
+        let color = self.capture(&rect)?.get_color(0, 0)?;
 
         Ok(color)
     }
@@ -325,7 +343,7 @@ impl YasScanner {
         Ok(pool)
     }
 
-    fn check_menu(&self) -> Result<()> {
+    fn check_menu(&mut self) -> Result<()> {
         if self.config.no_check {
             return Ok(());
         }
@@ -365,12 +383,12 @@ impl YasScanner {
             //     pixels[4 * i + 3]
             // );
         }
-        self.enigo.mouse_move_to(rect.left, rect.top + offset);
-        self.enigo.mouse_down(MouseButton::Left);
+        self.input.move_to(rect.left, rect.top + offset);
+        self.input.mouse_down();
         utils::sleep(self.config.default_stop);
-        self.enigo.mouse_move_to(rect.left, self.info.top + 10);
+        self.input.move_to(rect.left, self.info.top + 10);
         utils::sleep(self.config.default_stop);
-        self.enigo.mouse_up(MouseButton::Left);
+        self.input.mouse_up();
         Ok(())
     }
 
@@ -383,7 +401,7 @@ impl YasScanner {
         self.create_dumps_folder()?;
         // move focus to the first artifact
         self.move_to(0, 0);
-        self.enigo.mouse_click(MouseButton::Left);
+        self.input.click();
         utils::sleep(self.config.default_stop);
         // match ruler and ruler_shift to get scroll speed
         let ruler = self.get_ruler()?.data;
@@ -423,8 +441,8 @@ impl YasScanner {
     fn get_art_count(&mut self) -> Result<u32> {
         let count = self.config.number;
         if let 0 = count {
-            let info = &self.info;
-            let raw_after_pp = self.info.art_count_position.capture_relative(info)?;
+            let count_position = self.info.art_count_position.clone();
+            let raw_after_pp = self.capture_relative(&count_position)?;
             // raw_after_pp.to_gray_image().save("count.png");
             let s = self.model.inference_string(&raw_after_pp)?;
             info!("raw count string: {}", s);
@@ -571,6 +589,55 @@ impl YasScanner {
         Ok(())
     }
 
+    // waits up to max_wait_late_flip ms for the pending flips of the page to show,
+    // returns the last lock states read
+    fn wait_for_flips(
+        &mut self,
+        start_row: u32,
+        pending: &[(usize, u32, bool)],
+    ) -> Result<Vec<bool>> {
+        let now = SystemTime::now();
+        loop {
+            let locks = self.get_locks(start_row, false, false)?;
+            let flipped = pending
+                .iter()
+                .all(|&(p, _, should_be_locked)| locks[p] == should_be_locked);
+            if flipped || now.elapsed()?.as_millis() >= self.config.max_wait_late_flip as u128 {
+                return Ok(locks);
+            }
+            utils::sleep(self.config.flip_check_stop);
+        }
+    }
+
+    // open the artifact at (row, col) of the grid
+    fn select_artifact(&mut self, row: u32, col: u32) -> Result<RawCaptureImage> {
+        trace!("moving to ({}, {})", row, col);
+        self.move_to(row, col);
+        trace!("clicking");
+        self.input.click();
+        trace!("waiting for switch");
+        self.wait_until_switched()
+    }
+
+    // click the lock button of the opened artifact, `index` is its position
+    // on the page
+    fn click_lock(&mut self, start_row: u32, index: usize, should_be_locked: bool) -> Result<()> {
+        let left: i32 = self.info.left + self.info.lock_x as i32;
+        let top: i32 = self.info.top + self.info.lock_y as i32;
+
+        trace!("moving to lock");
+        self.input.move_to(left, top);
+        trace!("clicking");
+        self.input.click();
+        trace!("Sleeping for {}ms", self.config.lock_stop);
+        utils::sleep(self.config.lock_stop);
+        if self.config.max_wait_lock > 0 {
+            trace!("waiting for flip");
+            self.wait_until_flipped(start_row, index, should_be_locked)?;
+        }
+        Ok(())
+    }
+
     fn capture_panel(&mut self) -> Result<RawCaptureImage> {
         let rect: PixelRect = PixelRect {
             left: self.info.left as i32 + self.info.panel_position.left,
@@ -620,9 +687,8 @@ impl YasScanner {
     fn get_locks(&mut self, start_row: u32, focus: bool, mark: bool) -> Result<Vec<bool>> {
         // move focus out of all artifacts
         if focus {
-            self.enigo
-                .mouse_move_to(self.info.left + 10, self.info.top + 10);
-            self.enigo.mouse_click(MouseButton::Left);
+            self.input.move_to(self.info.left + 10, self.info.top + 10);
+            self.input.click();
             utils::sleep(self.config.default_stop);
         }
         // capture game screen
@@ -689,7 +755,7 @@ impl YasScanner {
         fs::create_dir("captures")?;
         let info = &self.info.clone();
 
-        let count = self.info.art_count_position.capture_relative(info)?;
+        let count = self.capture_relative(&info.art_count_position)?;
         count.to_gray_image().save("captures/count.png")?;
 
         let panel = self.capture_panel()?;
@@ -779,7 +845,7 @@ impl YasScanner {
         Ok(())
     }
 
-    pub fn scan(&mut self) -> Result<Vec<InternalArtifact>> {
+    pub fn scan(&mut self) -> Result<Vec<ArtifactRecord>> {
         // self.align_panel();
         self.check_menu()?;
         self.scroll_to_top()?;
@@ -805,176 +871,55 @@ impl YasScanner {
 
         let (tx, rx) = mpsc::channel::<Option<(RawCaptureImage, u32, bool)>>();
         let info_2 = self.info.clone();
-        // v bvvmnvbm
         let is_dump_mode = self.config.dump_mode;
         let min_level = self.config.min_level;
-        let handle = thread::spawn(move || -> Result<Vec<InternalArtifact>> {
-            let mut results: Vec<InternalArtifact> = Vec::new();
-            let model = CRNNModel::new()?;
-            let mut error_count = 0;
-            let mut dup_count = 0;
-            let mut hash = HashSet::new();
-            let mut consecutive_dup_count = 0;
-            let info = info_2;
-
-            let mut cnt = 0;
-            if is_dump_mode {
-                fs::create_dir("dumps")?;
-            }
-
-            for i in rx {
-                let (capture, rarity, lock) = match i {
-                    Some(v) => v,
-                    None => break,
+        let min_confidence = self.config.min_confidence;
+        let model_loader = self.model_loader.clone();
+        let save_panels = self.config.save_panels.clone();
+        let handle = thread::spawn(move || -> Result<Vec<ArtifactRecord>> {
+            let mut saver = match save_panels {
+                Some(dir) => Some(PanelSaver::new(Path::new(&dir), &info_2)?),
+                None => None,
+            };
+            let mut recognizer = ArtifactRecognizer::with_model(
+                info_2,
+                model_loader()?,
+                is_dump_mode,
+                min_confidence,
+            )?;
+
+            let mut finished = false;
+            while !finished {
+                // wait for one panel, then take whatever else is already queued
+                let mut batch = match rx.recv() {
+                    Ok(Some(v)) => vec![v],
+                    _ => break,
                 };
-                // let now = SystemTime::now();
-
-                let model_inference = |pos: &PixelRect, name: &str, cnt: i32| -> Result<String> {
-                    let raw_img = capture.crop_to_raw_img(&pos);
-                    if is_dump_mode {
-                        raw_img
-                            .grayscale_to_gray_image()
-                            .save(format!("dumps/{}_{}.png", name, cnt))?;
-                    }
-
-                    let processed_img = pre_process(raw_img);
-
-                    if processed_img.w == 0 || processed_img.h == 0 {
-                        return Ok(String::from(""));
-                    }
-
-                    if is_dump_mode {
-                        processed_img
-                            .to_gray_image()
-                            .save(format!("dumps/p_{}_{}.png", name, cnt))?;
-                    }
-                    let inference_result = model.inference_string(&processed_img)?;
-                    if is_dump_mode {
-                        fs::write(format!("dumps/{}_{}.txt", name, cnt), &inference_result)?;
+                while batch.len() < BATCH_SIZE {
+                    match rx.try_recv() {
+                        Ok(Some(v)) => batch.push(v),
+                        Ok(None) => {
+                            finished = true;
+                            break;
+                        }
+                        Err(_) => break,
                     }
+                }
 
-                    Ok(inference_result)
-                };
-
-                let str_title = model_inference(&info.title_position, "title", cnt)?;
-                let str_main_stat_name =
-                    model_inference(&info.main_stat_name_position, "main_stat_name", cnt)?;
-                let str_main_stat_value =
-                    model_inference(&info.main_stat_value_position, "main_stat_value", cnt)?;
-
-                let str_sub_stat_1 = model_inference(&info.sub_stat1_position, "sub_stat_1", cnt)?;
-                let str_sub_stat_2 = model_inference(&info.sub_stat2_position, "sub_stat_2", cnt)?;
-                let str_sub_stat_3 = model_inference(&info.sub_stat3_position, "sub_stat_3", cnt)?;
-                let str_sub_stat_4 = model_inference(&info.sub_stat4_position, "sub_stat_4", cnt)?;
-
-                let str_level = model_inference(&info.level_position, "level", cnt)?;
-                let str_equip = model_inference(&info.equip_position, "equip", cnt)?;
-
-                cnt += 1;
-
-                // let predict_time = now.elapsed().unwrap().as_millis();
-                // println!("predict time: {}ms", predict_time);
-
-                let result = YasScanResult {
-                    name: str_title,
-                    main_stat_name: str_main_stat_name,
-                    main_stat_value: str_main_stat_value,
-                    sub_stat_1: str_sub_stat_1,
-                    sub_stat_2: str_sub_stat_2,
-                    sub_stat_3: str_sub_stat_3,
-                    sub_stat_4: str_sub_stat_4,
-                    level: str_level,
-                    location: str_equip,
-                    rarity,
-                    lock,
-                };
-                debug!("{:?}", result);
-                // println!("{:?}", result);
-                let art = result.to_internal_artifact();
-                if let Some(a) = art {
-                    if hash.contains(&a) {
-                        dup_count += 1;
-                        consecutive_dup_count += 1;
-                        warn!("dup artifact detected: {:?}", result);
-                    } else {
-                        consecutive_dup_count = 0;
-                        hash.insert(a.clone());
-                        // results.push(a);
-                    }
-                    results.push(a);
-                } else {
-                    error!("wrong detection: {:?}", result);
-                    //使得在有Error下也能输出对应个数，方便lock
-                    if true
-                    {
-                        let set_key = ArtifactSetKey::from_zh_cn(&result.name).unwrap_or(ArtifactSetKey::GladiatorsFinale);//角斗士 花
-                        let slot_key = ArtifactSlotKey::from_zh_cn(&result.name).unwrap_or(ArtifactSlotKey::Flower);
-                        let rarity = result.rarity;
-                        let mut level:u32 = 0;
-                        if !result.level.contains("+") {
-                            level = result
-                            .level
-                            .chars()
-                            .skip(1)
-                            .collect::<String>()
-                            .parse::<u32>()
-                            .ok().unwrap_or(0);
-                        }
-                        let main_stat = ArtifactStat::from_zh_cn_raw(
-                            (result.main_stat_name.replace("+", "?") + "+" + result.main_stat_value.as_str()).as_str(),
-                        ).unwrap_or(
-                            ArtifactStat {
-                                key: ArtifactStatKey::Hp,
-                                value: 4780.0,
-                            }
-                        );
-                        let sub1 = ArtifactStat::from_zh_cn_raw(&result.sub_stat_1);
-                        let sub2 = ArtifactStat::from_zh_cn_raw(&result.sub_stat_2);
-                        let sub3 = ArtifactStat::from_zh_cn_raw(&result.sub_stat_3);
-                        let sub4 = ArtifactStat::from_zh_cn_raw(&result.sub_stat_4);
-
-                        let location = if result.location.contains("已装备") {
-                            let len = result.location.chars().count();
-                            CharacterKey::from_zh_cn(&result.location.chars().take(len - 3).collect::<String>())
-                        } else {
-                            None
-                        };
-
-                        let tart = InternalArtifact {
-                            set_key,
-                            slot_key,
-                            rarity,
-                            level,
-                            location,
-                            lock: result.lock,
-                            main_stat,
-                            sub_stat_1: sub1,
-                            sub_stat_2: sub2,
-                            sub_stat_3: sub3,
-                            sub_stat_4: sub4,
-                        };
-                        results.push(tart);
+                if let Some(saver) = saver.as_mut() {
+                    for (capture, rarity, lock) in batch.iter() {
+                        saver.save(capture, *rarity, *lock)?;
                     }
-                    error_count += 1;
-                    // println!("error parsing results");
                 }
-                if consecutive_dup_count >= info.art_row {
-                    error!("检测到连续多个重复圣遗物，可能为翻页错误，或者为非背包顶部开始扫描");
+                if !recognizer.push_batch(&batch)? {
                     break;
                 }
             }
 
-            info!("error count: {}", error_count);
-            info!("dup count: {}", dup_count);
-
-            Ok(if min_level > 0 {
-                results
-                    .into_iter()
-                    .filter(|result| result.level >= min_level)
-                    .collect::<Vec<_>>()
-            } else {
-                results
-            })
+            if let Some(saver) = saver {
+                saver.finish()?;
+            }
+            recognizer.finish(min_level)
         });
 
         let mut scanned_row = 0_u32;
@@ -983,7 +928,7 @@ impl YasScanner {
         // let mut lock = false;
 
         // self.move_to(0, 0);
-        // self.enigo.mouse_click(MouseButton::Left);
+        // self.input.click();
         // utils::sleep(1000);
         // self.sample_initial_color();
         // let mut now = SystemTime::now();
@@ -1006,7 +951,7 @@ impl YasScanner {
                     }
 
                     self.move_to(row, col);
-                    self.enigo.mouse_click(MouseButton::Left);
+                    self.input.click();
 
                     let capture = self.wait_until_switched()?;
 
@@ -1054,16 +999,21 @@ impl YasScanner {
         tx.send(None)?;
 
         info!("扫描结束，等待识别线程结束，请勿关闭程序");
-        let results: Vec<InternalArtifact> =
+        let results: Vec<ArtifactRecord> =
             handle.join().map_err(|_| anyhow!("thread join err"))??;
         info!("count: {}", results.len());
         Ok(results)
     }
 
-    pub fn lock(&mut self, actions: Vec<LockAction>) -> Result<()> {
+    // `journal` gets every target right after its lock is clicked
+    pub fn lock(
+        &mut self,
+        actions: Vec<LockAction>,
+        mut journal: Option<LockJournal>,
+    ) -> Result<LockReport> {
         if actions.len() == 0 {
             info!("no lock actions");
-            return Ok(());
+            return Ok(LockReport::default());
         }
 
         // self.align_panel();
@@ -1097,6 +1047,22 @@ impl YasScanner {
 
         trace!("initial pool: {}", self.pool);
 
+        // v3 targets are OCR'd before they are flipped
+        let mut recognizer = if actions.iter().any(|a| a.fingerprint.is_some()) {
+            Some(ArtifactRecognizer::with_model(
+                self.info.clone(),
+                (self.model_loader)()?,
+                false,
+                self.config.min_confidence,
+            )?)
+        } else {
+            None
+        };
+        let mut report = LockReport::default();
+        // flipped on the current page and not confirmed yet: (position on the
+        // page, target, should be locked)
+        let mut pending: Vec<(usize, u32, bool)> = Vec::new();
+
         // loop over pages
         'outer: while end_action < actions.len() {
             if utils::is_rmb_down() {
@@ -1106,20 +1072,14 @@ impl YasScanner {
             start_action = end_action;
             start_art = self.col * (scrolled_rows + start_row);
             end_art = min(self.col * (scrolled_rows + self.row), total_arts);
-            let mut should_get_locks = self.config.max_wait_lock > 0;
-            let mut locks: Vec<bool> = Vec::new();
 
             // get actions inside current page
             while end_action < actions.len() && actions[end_action].target < end_art {
-                if actions[end_action].type_ != LockActionType::Flip {
-                    should_get_locks = true;
-                }
                 end_action += 1;
             }
 
-            if should_get_locks {
-                locks = self.get_locks(start_row, true, self.config.mark)?;
-            }
+            // also needed to check the flips afterwards
+            let locks = self.get_locks(start_row, true, self.config.mark)?;
 
             // validate
             for i in start_action..end_action {
@@ -1140,39 +1100,91 @@ impl YasScanner {
             for i in start_action..end_action {
                 let a = &actions[i];
                 let p = (a.target - start_art) as usize;
-                if (a.type_ == LockActionType::Lock && !locks[p])
+                let should_flip = (a.type_ == LockActionType::Lock && !locks[p])
                     || (a.type_ == LockActionType::Unlock && locks[p])
-                    || a.type_ == LockActionType::Flip
-                {
+                    || a.type_ == LockActionType::Flip;
+                if !should_flip && a.fingerprint.is_none() {
+                    continue;
+                }
+
+                if utils::is_rmb_down() {
+                    break 'outer;
+                }
+
+                let r = p as u32 / self.col + start_row;
+                let c = p as u32 % self.col;
+                let capture = self.select_artifact(r, c)?;
+
+                if let (Some(fingerprint), Some(recognizer)) = (&a.fingerprint, &mut recognizer) {
+                    let star = self.get_star(&capture)?;
+                    let result = recognizer
+                        .recognize_batch(&[(capture, star, locks[p])])?
+                        .remove(0);
+                    let reason = match result.to_fingerprint() {
+                        Ok(found) => fingerprint.diff(&found),
+                        Err(e) => Some(e.to_string()),
+                    };
+                    if let Some(reason) = reason {
+                        warn!("第{}个圣遗物与lock.json不符，跳过: {}", a.target, reason);
+                        report.mismatched.push(a.target);
+                        continue;
+                    }
+                }
+
+                if !should_flip {
+                    continue;
+                }
+
+                debug!("flip lock of {} at ({}, {})", a.target, r, c);
+                self.click_lock(start_row, p, !locks[p])?;
+                if let Some(journal) = journal.as_mut() {
+                    journal.record(a.target, !locks[p])?;
+                }
+                pending.push((p, a.target, !locks[p]));
+            }
+
+            // check the flips of this page, clicking again the ones that did
+            // not take
+            let mut retries = 0;
+            while !pending.is_empty() {
+                let mut locks = self.get_locks(start_row, true, false)?;
+                // a flip can show up late, clicking again right away would
+                // toggle it back
+                if pending.iter().any(|&(p, _, l)| locks[p] != l) {
+                    locks = self.wait_for_flips(start_row, &pending)?;
+                }
+                let (done, wrong): (Vec<_>, Vec<_>) = pending
+                    .drain(..)
+                    .partition(|&(p, _, should_be_locked)| locks[p] == should_be_locked);
+                for (_, target, _) in done {
+                    if retries == 0 {
+                        report.succeeded.push(target);
+                    } else {
+                        report.retried.push(target);
+                    }
+                }
+                pending = wrong;
+                if pending.is_empty() {
+                    break;
+                }
+                if retries >= self.config.lock_retry {
+                    for (_, target, _) in pending.drain(..) {
+                        warn!("第{}个圣遗物加解锁失败", target);
+                        report.failed.push(target);
+                    }
+                    break;
+                }
+
+                retries += 1;
+                for &(p, target, should_be_locked) in pending.iter() {
                     if utils::is_rmb_down() {
                         break 'outer;
                     }
-
+                    warn!("第{}个圣遗物加解锁未生效，重试第{}次", target, retries);
                     let r = p as u32 / self.col + start_row;
                     let c = p as u32 % self.col;
-
-                    debug!("flip lock of {} at ({}, {})", a.target, r, c);
-
-                    trace!("moving to ({}, {})", r, c);
-                    self.move_to(r, c);
-                    trace!("clicking");
-                    self.enigo.mouse_click(MouseButton::Left);
-                    trace!("waiting for switch");
-                    self.wait_until_switched()?;
-
-                    let left: i32 = self.info.left + self.info.lock_x as i32;
-                    let top: i32 = self.info.top + self.info.lock_y as i32;
-
-                    trace!("moving to lock");
-                    self.enigo.mouse_move_to(left, top);
-                    trace!("clicking");
-                    self.enigo.mouse_click(MouseButton::Left);
-                    trace!("Sleeping for {}ms", self.config.lock_stop);
-                    utils::sleep(self.config.lock_stop);
-                    if self.config.max_wait_lock > 0 {
-                        trace!("waiting for flip");
-                        self.wait_until_flipped(start_row, p, !locks[p])?;
-                    }
+                    self.select_artifact(r, c)?;
+                    self.click_lock(start_row, p, should_be_locked)?;
                 }
             }
 
@@ -1190,6 +1202,304 @@ impl YasScanner {
             start_row = self.row - to_scroll_rows;
         }
 
-        Ok(())
+        // stopped with the right button before the page was checked
+        report
+            .unverified
+            .extend(pending.iter().map(|&(_, target, _)| target));
+        report.log();
+        Ok(report)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::artifact::internal_artifact::{
+        ArtifactSetKey, ArtifactSlotKey, ArtifactStat, ArtifactStatKey, CharacterKey,
+        InternalArtifact,
+    };
+    use crate::artifact::main_stat::main_stat_value;
+    use crate::input::{InputLog, RecordingDriver};
+    use crate::simulator::Simulator;
+
+    // different for every `i`, as the scan drops duplicates
+    fn artifact(i: u32, lock: bool) -> InternalArtifact {
+        let level = i % 21;
+        InternalArtifact {
+            set_key: ArtifactSetKey::GladiatorsFinale,
+            slot_key: ArtifactSlotKey::Flower,
+            rarity: 5,
+            level,
+            lock,
+            location: if i % 4 == 1 {
+                Some(CharacterKey::Bennett)
+            } else {
+                None
+            },
+            main_stat: ArtifactStat {
+                key: ArtifactStatKey::Hp,
+                value: main_stat_value(&ArtifactStatKey::Hp, 5, level).unwrap(),
+            },
+            sub_stat_1: Some(ArtifactStat {
+                key: ArtifactStatKey::ElementalMastery,
+                value: 16.0 + i as f64,
+            }),
+            sub_stat_2: Some(ArtifactStat {
+                key: ArtifactStatKey::CriticalDamage,
+                value: 7.8,
+            }),
+            sub_stat_3: None,
+            sub_stat_4: None,
+        }
+    }
+
+    fn backpack(n: u32, lock: impl Fn(u32) -> bool) -> Vec<InternalArtifact> {
+        (0..n).map(|i| artifact(i, lock(i))).collect()
+    }
+
+    fn info() -> ScanInfo {
+        ScanInfo::from_16_9(1280, 720, 0, 0)
+    }
+
+    // fast waits, the simulator reacts at once. The scroll speed, the count
+    // and the switches of the panel are all read from the screen.
+    fn config() -> YasScannerConfig {
+        YasScannerConfig {
+            max_row: 1000,
+            min_star: 1,
+            speed: 5,
+            max_wait_switch_artifact: 100,
+            max_wait_scroll: 100,
+            max_wait_late_flip: 500,
+            flip_check_stop: 50,
+            lock_retry: 2,
+            ..Default::default()
+        }
+    }
+
+    fn scanner(sim: &Simulator, input: Box<dyn InputDriver>) -> YasScanner {
+        scanner_with(sim, input, config())
+    }
+
+    fn scanner_with(
+        sim: &Simulator,
+        input: Box<dyn InputDriver>,
+        config: YasScannerConfig,
+    ) -> YasScanner {
+        YasScanner::with_model_loader(info(), config, sim.screen(), input, sim.model_loader())
+            .unwrap()
+    }
+
+    // one full page plus two rows and a bit
+    fn two_pages() -> u32 {
+        let info = info();
+        info.art_row * info.art_col + info.art_col * 2 + 3
+    }
+
+    #[test]
+    fn lock_clicks() {
+        let sim = Simulator::new(backpack(two_pages(), |_| false), info());
+        let driver = RecordingDriver::wrap(sim.input());
+        let log = driver.log();
+        let mut scanner = scanner(&sim, Box::new(driver));
+        let actions = LockAction::from_lock_json("[2, 5]").unwrap();
+        scanner.lock(actions, None).unwrap();
+
+        let info = info();
+        let card = |i: u32| {
+            let (row, col) = (i / info.art_col, i % info.art_col);
+            (
+                info.left_margin as i32
+                    + info.art_width as i32 / 2
+                    + (info.art_shift_x * col as f64) as i32,
+                info.top_margin as i32
+                    + info.art_height as i32 / 2
+                    + (info.art_shift_y * row as f64) as i32,
+            )
+        };
+        let focus = (10, 10);
+        let lock = (info.lock_x as i32, info.lock_y as i32);
+        // the first artifact is opened to measure the scroll speed
+        assert_eq!(
+            log.clicks(),
+            vec![card(0), focus, card(2), lock, card(5), lock, focus]
+        );
+        assert_eq!(sim.visits(), vec![0, 2, 5]);
+        let locked: Vec<usize> = (0..two_pages() as usize)
+            .filter(|&i| sim.locks()[i])
+            .collect();
+        assert_eq!(locked, vec![2, 5]);
+    }
+
+    #[test]
+    fn scan_two_pages() {
+        let n = two_pages();
+        let sim = Simulator::new(backpack(n, |i| i % 3 == 0), info());
+        let mut scanner = scanner(&sim, sim.input());
+        let records = scanner.scan().unwrap();
+
+        // every slot opened once, in order, after the first one was opened to
+        // measure the scroll speed, and read back as it was drawn
+        let mut visits = vec![0];
+        visits.extend(0..n as usize);
+        assert_eq!(sim.visits(), visits);
+        assert_eq!(
+            records.iter().map(|r| r.index()).collect::<Vec<_>>(),
+            (0..n as usize).collect::<Vec<_>>()
+        );
+        let scanned: Vec<InternalArtifact> = records
+            .into_iter()
+            .map(|r| match r {
+                ArtifactRecord::Recognized { artifact, .. } => artifact,
+                ArtifactRecord::Unrecognized { index, error, .. } => {
+                    panic!("{} not recognized: {}", index, error)
+                }
+                ArtifactRecord::Skipped { index } => panic!("{} skipped", index),
+            })
+            .collect();
+        assert_eq!(scanned, sim.artifacts());
+    }
+
+    #[test]
+    fn lock_two_pages() {
+        let n = two_pages();
+        let sim = Simulator::new(backpack(n, |i| i % 3 == 0), info());
+        let mut scanner = scanner(&sim, sim.input());
+        let last = n - 1;
+        let json = format!(
+            r#"{{"version": 2, "flip_indices": [1, {}], "lock_indices": [2, 4, {}],
+                "unlock_indices": [3, {}], "validation": [{{"index": 6, "locked": true}}]}}"#,
+            last - 3,
+            last,
+            last - 2
+        );
+        let actions = LockAction::from_lock_json(&json).unwrap();
+        let report = scanner.lock(actions, None).unwrap();
+
+        let mut expected: Vec<bool> = (0..n).map(|i| i % 3 == 0).collect();
+        for &i in [1, last - 3].iter() {
+            expected[i as usize] = !expected[i as usize];
+        }
+        for &i in [2, 4, last].iter() {
+            expected[i as usize] = true;
+        }
+        expected[3] = false;
+        expected[last as usize - 2] = false;
+        assert_eq!(sim.locks(), expected);
+        assert!(report.ok());
+        assert!(sim.scroll_offset() > 0.0);
+    }
+
+    // loses the first `drop` clicks on the lock button
+    struct DropLockClicks {
+        inner: Box<dyn InputDriver>,
+        cursor: (i32, i32),
+        drop: u32,
+    }
+
+    impl InputDriver for DropLockClicks {
+        fn move_to(&mut self, x: i32, y: i32) {
+            self.cursor = (x, y);
+            self.inner.move_to(x, y);
+        }
+
+        fn click(&mut self) {
+            let info = info();
+            if self.cursor == (info.lock_x as i32, info.lock_y as i32) && self.drop > 0 {
+                self.drop -= 1;
+                return;
+            }
+            self.inner.click();
+        }
+
+        fn scroll(&mut self, length: i32) {
+            self.inner.scroll(length);
+        }
+
+        fn mouse_down(&mut self) {
+            self.inner.mouse_down();
+        }
+
+        fn mouse_up(&mut self) {
+            self.inner.mouse_up();
+        }
+    }
+
+    fn lock_with(sim: &Simulator, drop: u32) -> (LockReport, InputLog) {
+        lock_with_config(sim, drop, config())
+    }
+
+    fn lock_with_config(
+        sim: &Simulator,
+        drop: u32,
+        config: YasScannerConfig,
+    ) -> (LockReport, InputLog) {
+        let driver = RecordingDriver::wrap(Box::new(DropLockClicks {
+            inner: sim.input(),
+            cursor: (0, 0),
+            drop,
+        }));
+        let log = driver.log();
+        let mut scanner = scanner_with(sim, Box::new(driver), config);
+        let actions = LockAction::from_lock_json("[2, 5]").unwrap();
+        (scanner.lock(actions, None).unwrap(), log)
+    }
+
+    fn count_lock_clicks(log: &InputLog) -> usize {
+        let info = info();
+        let lock = (info.lock_x as i32, info.lock_y as i32);
+        log.clicks().into_iter().filter(|&c| c == lock).count()
+    }
+
+    #[test]
+    fn lock_retries_dropped_click() {
+        let sim = Simulator::new(backpack(two_pages(), |_| false), info());
+        let (report, log) = lock_with(&sim, 1);
+        assert_eq!(report.succeeded, vec![5]);
+        assert_eq!(report.retried, vec![2]);
+        assert!(report.failed.is_empty());
+        assert_eq!(count_lock_clicks(&log), 3);
+        assert!(sim.locks()[2] && sim.locks()[5]);
+    }
+
+    #[test]
+    fn lock_reports_failed() {
+        let sim = Simulator::new(backpack(two_pages(), |_| false), info());
+        let (report, _) = lock_with(&sim, u32::MAX);
+        assert!(report.succeeded.is_empty() && report.retried.is_empty());
+        assert_eq!(report.failed, vec![2, 5]);
+        assert!(!report.ok());
+    }
+
+    #[test]
+    fn lock_waits_for_late_flip() {
+        let sim = Simulator::new(backpack(two_pages(), |_| false), info()).with_lock_delay(200);
+        let (report, log) = lock_with(&sim, 0);
+        assert_eq!(report.succeeded, vec![2, 5]);
+        assert!(report.retried.is_empty());
+        // not clicked again, which would have unlocked them
+        assert_eq!(count_lock_clicks(&log), 2);
+        assert!(sim.locks()[2] && sim.locks()[5]);
+    }
+
+    #[test]
+    fn late_flip_wait_is_configured() {
+        // the flips show after several checks, without retries they only
+        // succeed if the scanner waits long enough
+        let config = |wait| YasScannerConfig {
+            max_wait_late_flip: wait,
+            flip_check_stop: 20,
+            lock_retry: 0,
+            ..config()
+        };
+        let sim = Simulator::new(backpack(two_pages(), |_| false), info()).with_lock_delay(150);
+        let (report, log) = lock_with_config(&sim, 0, config(1000));
+        assert_eq!(report.succeeded, vec![2, 5]);
+        assert_eq!(count_lock_clicks(&log), 2);
+
+        let sim = Simulator::new(backpack(two_pages(), |_| false), info()).with_lock_delay(300);
+        let (report, _) = lock_with_config(&sim, 0, config(0));
+        assert!(report.succeeded.is_empty());
+        assert_eq!(report.failed, vec![2, 5]);
     }
 }
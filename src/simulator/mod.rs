@@ -0,0 +1,663 @@
+use anyhow::Result;
+use std::cell::RefCell;
+use std::rc::Rc;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use crate::artifact::internal_artifact::{ArtifactStat, ArtifactStatKey, InternalArtifact};
+use crate::artifact::names::names;
+use crate::capture::screen_source::ScreenSource;
+use crate::common::color::Color;
+use crate::common::{PixelRect, RawCaptureImage, RawImage};
+use crate::inference::inference::{InferenceResult, ModelLoader, TextModel};
+use crate::info::info::ScanInfo;
+use crate::input::InputDriver;
+
+// A headless backpack that YasScanner can be driven against: it renders the
+// pixels the scanner samples (menu, artifact grid with lock icons, detail
+// panel with star colour and substat area, scrollbar) and reacts to clicks,
+// wheel steps, scrollbar drags and the lock button.
+//
+// Text (the `圣遗物 <count>/1800` count and every field of the panel) is
+// drawn with a made-up block font that the real model can't read, so scans
+// have to use `model_loader()`, which reads it back exactly. `visits()` tells
+// which artifacts were opened. `with_lock_delay` makes the lock button slow.
+
+const MENU_COLOR: Color = Color(236, 229, 216);
+const BACKGROUND_COLOR: Color = Color(50, 50, 60);
+const PANEL_COLOR: Color = Color(70, 70, 80);
+const LOCK_COLOR: Color = Color(255, 138, 117);
+const LEVEL_BAND_COLOR: Color = Color(233, 229, 220);
+const SCROLLBAR_COLOR: Color = Color(80, 80, 80);
+const THUMB_COLOR: Color = Color(220, 220, 220);
+const TEXT_COLOR: Color = Color(210, 200, 185);
+const STAR_COLORS: [Color; 5] = [
+    Color(113, 119, 139),
+    Color(42, 143, 114),
+    Color(81, 127, 203),
+    Color(161, 86, 224),
+    Color(188, 105, 50),
+];
+
+// Every character is a block of 3x5 cells with a blank column after it. The
+// top and bottom rows are always set, so that pre_process crops all text the
+// same way, the 9 cells between them hold the number of the character in a
+// table shared with SimulatedModel.
+const GLYPH_CELLS: (usize, usize) = (3, 5);
+
+type Glyphs = Arc<Mutex<Vec<char>>>;
+
+fn glyph_code(glyphs: &Glyphs, c: char) -> usize {
+    let mut glyphs = glyphs.lock().unwrap();
+    match glyphs.iter().position(|&g| g == c) {
+        Some(i) => i + 1,
+        None => {
+            glyphs.push(c);
+            glyphs.len()
+        }
+    }
+}
+
+// whether a cell of a glyph is set, `col` in 0..3 and `row` in 0..5
+fn glyph_cell(code: usize, col: usize, row: usize) -> bool {
+    let (w, h) = GLYPH_CELLS;
+    if row == 0 || row == h - 1 {
+        return true;
+    }
+    code >> ((row - 1) * w + col) & 1 == 1
+}
+
+// Whether a pixel of a `width` x `height` text box is set. The text is as
+// large as fits, with glyphs taking 5/7 of the height.
+fn text_pixel(codes: &[usize], width: i32, height: i32, x: i32, y: i32) -> bool {
+    if codes.is_empty() {
+        return false;
+    }
+    let cells = codes.len() as i32 * 4 - 1;
+    let cell = (height / 7).min(width / cells).max(1);
+    let (cx, cy) = (x / cell, y / cell - 1);
+    if !(0..5).contains(&cy) || cx % 4 == 3 {
+        return false;
+    }
+    match codes.get((cx / 4) as usize) {
+        Some(&code) => glyph_cell(code, (cx % 4) as usize, cy as usize),
+        None => false,
+    }
+}
+
+fn is_percentage(key: &ArtifactStatKey) -> bool {
+    !matches!(
+        key,
+        ArtifactStatKey::Atk
+            | ArtifactStatKey::Hp
+            | ArtifactStatKey::Def
+            | ArtifactStatKey::ElementalMastery
+    )
+}
+
+fn stat_name(key: &ArtifactStatKey) -> &'static str {
+    match key {
+        ArtifactStatKey::HealingBonus => "治疗加成",
+        ArtifactStatKey::CriticalDamage => "暴击伤害",
+        ArtifactStatKey::Critical => "暴击率",
+        ArtifactStatKey::Atk | ArtifactStatKey::AtkPercentage => "攻击力",
+        ArtifactStatKey::ElementalMastery => "元素精通",
+        ArtifactStatKey::Recharge => "元素充能效率",
+        ArtifactStatKey::Hp | ArtifactStatKey::HpPercentage => "生命值",
+        ArtifactStatKey::Def | ArtifactStatKey::DefPercentage => "防御力",
+        ArtifactStatKey::ElectroBonus => "雷元素伤害加成",
+        ArtifactStatKey::PyroBonus => "火元素伤害加成",
+        ArtifactStatKey::HydroBonus => "水元素伤害加成",
+        ArtifactStatKey::CryoBonus => "冰元素伤害加成",
+        ArtifactStatKey::AnemoBonus => "风元素伤害加成",
+        ArtifactStatKey::GeoBonus => "岩元素伤害加成",
+        ArtifactStatKey::PhysicalBonus => "物理伤害加成",
+        ArtifactStatKey::DendroBonus => "草元素伤害加成",
+    }
+}
+
+fn stat_value(stat: &ArtifactStat) -> String {
+    if is_percentage(&stat.key) {
+        format!("{:.1}%", stat.value)
+    } else {
+        format!("{}", stat.value)
+    }
+}
+
+// the text of a frame, worked out once per capture
+struct Texts {
+    count: Vec<usize>,
+    // fields of the panel, relative to it
+    fields: Vec<(PixelRect, Vec<usize>)>,
+}
+
+struct Drag {
+    start_y: i32,
+    start_scroll: f64,
+}
+
+struct VirtualBackpack {
+    artifacts: Vec<InternalArtifact>,
+    info: ScanInfo,
+
+    pixels_per_scroll: f64,
+    // how far the grid content has been scrolled, in pixels
+    scroll: f64,
+    selected: Option<usize>,
+    visits: Vec<usize>,
+
+    cursor: (i32, i32),
+    drag: Option<Drag>,
+
+    // lock button clicks show up this late, like a slow game
+    lock_delay: Duration,
+    late_flips: Vec<(usize, Instant)>,
+
+    glyphs: Glyphs,
+}
+
+impl VirtualBackpack {
+    fn total_rows(&self) -> u32 {
+        let (len, col) = (self.artifacts.len() as u32, self.info.art_col);
+        len / col + (len % col != 0) as u32
+    }
+
+    fn apply_late_flips(&mut self) {
+        let now = Instant::now();
+        let artifacts = &mut self.artifacts;
+        self.late_flips.retain(|&(i, at)| {
+            if at > now {
+                return true;
+            }
+            artifacts[i].lock = !artifacts[i].lock;
+            false
+        });
+    }
+
+    fn max_scroll(&self) -> f64 {
+        let hidden_rows = self.total_rows().saturating_sub(self.info.art_row);
+        hidden_rows as f64 * self.info.art_shift_y
+    }
+
+    fn set_scroll(&mut self, scroll: f64) {
+        self.scroll = scroll.max(0.0).min(self.max_scroll());
+    }
+
+    fn thumb(&self) -> (i32, i32) {
+        let track = self.info.scrollbar_height as f64;
+        let total = self.total_rows().max(1) as f64;
+        let height = (track * (self.info.art_row as f64 / total).min(1.0)).max(10.0);
+        let max_scroll = self.max_scroll();
+        let top = if max_scroll > 0.0 {
+            (track - height) * self.scroll / max_scroll
+        } else {
+            0.0
+        };
+        let top = self.info.scrollbar_top as i32 + top.round() as i32;
+        (top, top + height.round() as i32)
+    }
+
+    // artifact index and position inside the card, for a point in the grid
+    fn card_at(&self, x: i32, y: i32) -> Option<(usize, f64, f64)> {
+        let info = &self.info;
+        let gx = x as f64 - info.left_margin;
+        let gy = y as f64 - info.top_margin;
+        if gx < 0.0
+            || gy < 0.0
+            || gx >= info.art_shift_x * info.art_col as f64
+            || gy >= info.art_shift_y * info.art_row as f64
+        {
+            return None;
+        }
+        let cy = gy + self.scroll;
+        let col = (gx / info.art_shift_x).floor();
+        let row = (cy / info.art_shift_y).floor();
+        let in_x = gx - col * info.art_shift_x;
+        let in_y = cy - row * info.art_shift_y;
+        if in_x >= info.art_width as f64 || in_y >= info.art_height as f64 {
+            return None;
+        }
+        let index = row as usize * info.art_col as usize + col as usize;
+        if index >= self.artifacts.len() {
+            return None;
+        }
+        Some((index, in_x, in_y))
+    }
+
+    fn star_color(&self, index: usize) -> &Color {
+        let rarity = self.artifacts[index].rarity.clamp(1, 5);
+        &STAR_COLORS[rarity as usize - 1]
+    }
+
+    fn codes(&self, text: &str) -> Vec<usize> {
+        text.chars().map(|c| glyph_code(&self.glyphs, c)).collect()
+    }
+
+    fn texts(&self) -> Texts {
+        let count = self.codes(&format!("圣遗物 {}/1800", self.artifacts.len()));
+        let art = match self.selected {
+            Some(i) => &self.artifacts[i],
+            None => {
+                return Texts {
+                    count,
+                    fields: Vec::new(),
+                }
+            }
+        };
+
+        let info = &self.info;
+        let sub_stat = |stat: &Option<ArtifactStat>| match stat {
+            Some(stat) => format!("{}+{}", stat_name(&stat.key), stat_value(stat)),
+            None => String::new(),
+        };
+        let fields = vec![
+            (
+                &info.title_position,
+                names()
+                    .piece_name(&art.set_key, &art.slot_key)
+                    .unwrap_or_default()
+                    .to_string(),
+            ),
+            (
+                &info.main_stat_name_position,
+                stat_name(&art.main_stat.key).to_string(),
+            ),
+            (&info.main_stat_value_position, stat_value(&art.main_stat)),
+            (&info.sub_stat1_position, sub_stat(&art.sub_stat_1)),
+            (&info.sub_stat2_position, sub_stat(&art.sub_stat_2)),
+            (&info.sub_stat3_position, sub_stat(&art.sub_stat_3)),
+            (&info.sub_stat4_position, sub_stat(&art.sub_stat_4)),
+            (&info.level_position, format!("+{}", art.level)),
+            (
+                &info.equip_position,
+                match &art.location {
+                    Some(c) => format!("{}已装备", names().character_name(c).unwrap_or_default()),
+                    None => String::new(),
+                },
+            ),
+        ];
+        Texts {
+            count,
+            fields: fields
+                .into_iter()
+                .map(|(rect, text)| (rect.clone(), self.codes(&text)))
+                .collect(),
+        }
+    }
+
+    // color of a pixel, in window coordinates
+    fn pixel(&self, x: i32, y: i32, texts: &Texts) -> Color {
+        let info = &self.info;
+
+        if (x - info.menu_x as i32).abs() <= 2 && (y - info.menu_y as i32).abs() <= 2 {
+            return MENU_COLOR;
+        }
+
+        if x == info.scrollbar_left as i32
+            && y >= info.scrollbar_top as i32
+            && y < (info.scrollbar_top + info.scrollbar_height) as i32
+        {
+            let (top, bottom) = self.thumb();
+            return if y >= top && y < bottom {
+                THUMB_COLOR
+            } else {
+                SCROLLBAR_COLOR
+            };
+        }
+
+        let count = &info.art_count_position;
+        if x >= count.left && y >= count.top && x < count.right && y < count.bottom {
+            let (w, h) = (count.right - count.left, count.bottom - count.top);
+            return if text_pixel(&texts.count, w, h, x - count.left, y - count.top) {
+                TEXT_COLOR
+            } else {
+                BACKGROUND_COLOR
+            };
+        }
+
+        let panel = &info.panel_position;
+        if x >= panel.left
+            && y >= panel.top
+            && x < panel.left + panel.width
+            && y < panel.top + panel.height
+        {
+            return self.panel_pixel(x - panel.left, y - panel.top, texts);
+        }
+
+        if let Some((index, in_x, in_y)) = self.card_at(x, y) {
+            if self.artifacts[index].lock
+                && (in_x - info.art_lock_x).abs() <= 4.0
+                && (in_y - info.art_lock_y).abs() <= 4.0
+            {
+                return LOCK_COLOR;
+            }
+            if in_y >= info.art_height as f64 * 0.822 {
+                return LEVEL_BAND_COLOR;
+            }
+            // vertical texture, so that the ruler can be matched after scrolling
+            let cy = (y as f64 - info.top_margin + self.scroll).floor() as i64;
+            let t = ((cy as u32).wrapping_mul(2654435761) >> 26) as u8;
+            let c = self.star_color(index);
+            return Color(c.0, c.1.wrapping_add(t), c.2);
+        }
+
+        BACKGROUND_COLOR
+    }
+
+    // color of a pixel, relative to the detail panel
+    fn panel_pixel(&self, x: i32, y: i32, texts: &Texts) -> Color {
+        let index = match self.selected {
+            Some(i) => i,
+            None => return PANEL_COLOR,
+        };
+        let info = &self.info;
+
+        if (x - info.star_x as i32).abs() <= 3 && (y - info.star_y as i32).abs() <= 3 {
+            let c = self.star_color(index);
+            return Color(c.0, c.1, c.2);
+        }
+
+        let inside = |r: &PixelRect| {
+            x >= r.left && y >= r.top && x < r.left + r.width && y < r.top + r.height
+        };
+        for (rect, codes) in texts.fields.iter() {
+            if inside(rect)
+                && text_pixel(codes, rect.width, rect.height, x - rect.left, y - rect.top)
+            {
+                return TEXT_COLOR;
+            }
+        }
+        if inside(&info.sub_stat1_position)
+            || inside(&info.sub_stat2_position)
+            || inside(&info.sub_stat3_position)
+            || inside(&info.sub_stat4_position)
+        {
+            // different for neighbours, so that the scanner sees the switch
+            return Color(20, 40 + (index * 67 % 180) as u8, 20);
+        }
+
+        PANEL_COLOR
+    }
+
+    fn click(&mut self) {
+        let (x, y) = self.cursor;
+        let info = &self.info;
+
+        if (x - info.lock_x as i32).abs() <= 10 && (y - info.lock_y as i32).abs() <= 10 {
+            if let Some(i) = self.selected {
+                if self.lock_delay > Duration::from_millis(0) {
+                    self.late_flips.push((i, Instant::now() + self.lock_delay));
+                } else {
+                    self.artifacts[i].lock = !self.artifacts[i].lock;
+                }
+            }
+            return;
+        }
+
+        if let Some((index, _, _)) = self.card_at(x, y) {
+            self.selected = Some(index);
+            self.visits.push(index);
+        }
+    }
+
+    fn move_to(&mut self, x: i32, y: i32) {
+        self.cursor = (x, y);
+        if let Some(drag) = &self.drag {
+            let (top, bottom) = self.thumb();
+            let free = self.info.scrollbar_height as f64 - (bottom - top) as f64;
+            if free > 0.0 {
+                let scroll =
+                    drag.start_scroll + (y - drag.start_y) as f64 / free * self.max_scroll();
+                self.set_scroll(scroll);
+            }
+        }
+    }
+
+    fn mouse_down(&mut self) {
+        let (x, y) = self.cursor;
+        if (x - self.info.scrollbar_left as i32).abs() <= 3 {
+            self.drag = Some(Drag {
+                start_y: y,
+                start_scroll: self.scroll,
+            });
+        }
+    }
+}
+
+pub struct Simulator {
+    backpack: Rc<RefCell<VirtualBackpack>>,
+}
+
+impl Simulator {
+    pub fn new(artifacts: Vec<InternalArtifact>, info: ScanInfo) -> Simulator {
+        // a whole number, so that the scanner can measure it
+        let pixels_per_scroll = (info.art_shift_y / 10.0).round();
+        Simulator {
+            backpack: Rc::new(RefCell::new(VirtualBackpack {
+                artifacts,
+                info,
+                pixels_per_scroll,
+                scroll: 0.0,
+                selected: None,
+                visits: Vec::new(),
+                cursor: (0, 0),
+                drag: None,
+                lock_delay: Duration::from_millis(0),
+                late_flips: Vec::new(),
+                glyphs: Arc::new(Mutex::new(Vec::new())),
+            })),
+        }
+    }
+
+    // pixels moved by one wheel step, defaults to a tenth of a row
+    pub fn with_pixels_per_scroll(self, pixels: f64) -> Simulator {
+        self.backpack.borrow_mut().pixels_per_scroll = pixels;
+        self
+    }
+
+    // start with the grid scrolled, e.g. to test scroll_to_top
+    pub fn with_scroll(self, pixels: f64) -> Simulator {
+        self.backpack.borrow_mut().set_scroll(pixels);
+        self
+    }
+
+    // lock button clicks take effect `ms` later
+    pub fn with_lock_delay(self, ms: u64) -> Simulator {
+        self.backpack.borrow_mut().lock_delay = Duration::from_millis(ms);
+        self
+    }
+
+    // models that read the text of this simulator
+    pub fn model_loader(&self) -> ModelLoader {
+        let glyphs = self.backpack.borrow().glyphs.clone();
+        Arc::new(move || {
+            Ok(Box::new(SimulatedModel {
+                glyphs: glyphs.clone(),
+            }) as Box<dyn TextModel>)
+        })
+    }
+
+    pub fn screen(&self) -> Box<dyn ScreenSource> {
+        Box::new(SimulatedScreen {
+            backpack: self.backpack.clone(),
+        })
+    }
+
+    pub fn input(&self) -> Box<dyn InputDriver> {
+        Box::new(SimulatedInput {
+            backpack: self.backpack.clone(),
+        })
+    }
+
+    pub fn artifacts(&self) -> Vec<InternalArtifact> {
+        self.backpack.borrow().artifacts.clone()
+    }
+
+    pub fn locks(&self) -> Vec<bool> {
+        self.backpack.borrow_mut().apply_late_flips();
+        self.backpack
+            .borrow()
+            .artifacts
+            .iter()
+            .map(|a| a.lock)
+            .collect()
+    }
+
+    // indices of the artifacts opened by clicking, in order
+    pub fn visits(&self) -> Vec<usize> {
+        self.backpack.borrow().visits.clone()
+    }
+
+    pub fn scroll_offset(&self) -> f64 {
+        self.backpack.borrow().scroll
+    }
+}
+
+struct SimulatedScreen {
+    backpack: Rc<RefCell<VirtualBackpack>>,
+}
+
+impl ScreenSource for SimulatedScreen {
+    fn capture(&mut self, rect: &PixelRect) -> Result<RawCaptureImage> {
+        self.backpack.borrow_mut().apply_late_flips();
+        let backpack = self.backpack.borrow();
+        let texts = backpack.texts();
+        let (w, h) = (rect.width as u32, rect.height as u32);
+        let mut im = RawCaptureImage {
+            data: vec![255; (w * h * 4) as usize],
+            w,
+            h,
+        };
+        for y in 0..h {
+            for x in 0..w {
+                let color = backpack.pixel(
+                    rect.left + x as i32 - backpack.info.left,
+                    rect.top + y as i32 - backpack.info.top,
+                    &texts,
+                );
+                im.set_color(x, y, &color)?;
+            }
+        }
+        Ok(im)
+    }
+}
+
+struct SimulatedInput {
+    backpack: Rc<RefCell<VirtualBackpack>>,
+}
+
+impl InputDriver for SimulatedInput {
+    fn move_to(&mut self, x: i32, y: i32) {
+        let mut backpack = self.backpack.borrow_mut();
+        let (left, top) = (backpack.info.left, backpack.info.top);
+        backpack.move_to(x - left, y - top);
+    }
+
+    fn click(&mut self) {
+        self.backpack.borrow_mut().click();
+    }
+
+    fn scroll(&mut self, length: i32) {
+        let mut backpack = self.backpack.borrow_mut();
+        let scroll = backpack.scroll - length as f64 * backpack.pixels_per_scroll;
+        backpack.set_scroll(scroll);
+    }
+
+    fn mouse_down(&mut self) {
+        self.backpack.borrow_mut().mouse_down();
+    }
+
+    fn mouse_up(&mut self) {
+        self.backpack.borrow_mut().drag = None;
+    }
+}
+
+// Reads the block font back from a pre-processed image, where the text is
+// scaled to the full height of 32 pixels.
+struct SimulatedModel {
+    glyphs: Glyphs,
+}
+
+impl SimulatedModel {
+    fn read(&self, img: &RawImage) -> InferenceResult {
+        let set = |x: f64, y: f64| {
+            let (x, y) = (x as u32, y as u32);
+            x < img.w && y < img.h && img.data[(y * img.w + x) as usize] > 0.5
+        };
+        let width = match (0..img.w)
+            .rev()
+            .find(|&x| (0..img.h).any(|y| set(x as f64, y as f64)))
+        {
+            Some(x) => x + 1,
+            None => return InferenceResult::empty(),
+        };
+
+        let cell = img.h as f64 / GLYPH_CELLS.1 as f64;
+        let len = ((width as f64 / cell + 1.0) / 4.0).round() as usize;
+        let glyphs = self.glyphs.lock().unwrap();
+        let text: String = (0..len)
+            .map(|k| {
+                let mut code = 0;
+                for row in 1..GLYPH_CELLS.1 - 1 {
+                    for col in 0..GLYPH_CELLS.0 {
+                        let x = ((k * 4 + col) as f64 + 0.5) * cell;
+                        let y = (row as f64 + 0.5) * cell;
+                        if set(x, y) {
+                            code |= 1 << ((row - 1) * GLYPH_CELLS.0 + col);
+                        }
+                    }
+                }
+                code
+            })
+            .map(
+                |code: usize| match code.checked_sub(1).and_then(|i| glyphs.get(i)) {
+                    Some(&c) => c,
+                    None => '?',
+                },
+            )
+            .collect();
+
+        InferenceResult {
+            char_confidences: vec![1.0; text.chars().count()],
+            text,
+            confidence: 1.0,
+        }
+    }
+}
+
+impl TextModel for SimulatedModel {
+    fn inference_batch(&self, imgs: &[&RawImage]) -> Result<Vec<InferenceResult>> {
+        Ok(imgs.iter().map(|img| self.read(img)).collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::inference::pre_process::pre_process;
+
+    #[test]
+    fn count_text_is_read_back() {
+        let info = ScanInfo::from_16_9(1280, 720, 0, 0);
+        let sim = Simulator::new(Vec::new(), info.clone());
+        let shot = sim
+            .screen()
+            .capture(&info.art_count_position.to_rect())
+            .unwrap();
+        let text_pixels = (0..shot.h)
+            .flat_map(|y| (0..shot.w).map(move |x| (x, y)))
+            .filter(|&(x, y)| shot.get_color(x, y).unwrap().eq(&TEXT_COLOR))
+            .count();
+        assert!(text_pixels > 0);
+
+        let rect = PixelRect {
+            left: 0,
+            top: 0,
+            width: shot.w as i32,
+            height: shot.h as i32,
+        };
+        let img = pre_process(shot.crop_to_raw_img(&rect));
+        let model = (sim.model_loader())().unwrap();
+        assert_eq!(model.inference_string(&img).unwrap(), "圣遗物 0/1800");
+    }
+}
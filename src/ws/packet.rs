@@ -1,5 +1,10 @@
 use crate::{
-    artifact::internal_artifact::InternalArtifact, expo::good::GoodFormat,
+    expo::good::GoodFormat,
+    lock::dry_run::DryRunReport,
+    lock::LockReport,
+    scanner::artifact_recognizer::{
+        recognized_artifacts, recognized_indices, unrecognized_indices, ArtifactRecord, ScanSummary,
+    },
     scanner::yas_scanner::YasScannerConfig,
 };
 use anyhow::Result;
@@ -26,31 +31,62 @@ impl ConfigNotifyData {
 #[derive(Debug, Default, Deserialize, Serialize)]
 pub struct ScanReqData {
     pub argv: Vec<String>,
+    // files written to output-dir, same as --format
+    pub formats: Option<Vec<String>>,
+}
+
+// Version 2: good_json only lists the recognized artifacts, so its positions
+// are no longer backpack indices. Each artifact has `index` (and
+// `mainStatValue`) instead, and the slots missing from it are in
+// unrecognized_indices. Version 1 packets had no `version`.
+pub const SCAN_RSP_VERSION: u32 = 2;
+
+fn scan_rsp_version_1() -> u32 {
+    1
 }
 
 #[derive(Debug, Default, Deserialize, Serialize)]
 pub struct ScanRspData {
+    #[serde(default = "scan_rsp_version_1")]
+    pub version: u32,
     pub success: bool,
     pub message: String,
     pub good_json: String,
+    // backpack indices missing from good_json, use the `index` of each
+    // artifact in good_json for LockReq and lock.json
+    #[serde(default)]
+    pub unrecognized_indices: Vec<u32>,
+    // slots scanned, including those left out by --min-level, 0 if unknown
+    #[serde(default)]
+    pub art_count: u32,
 }
 
 impl ScanRspData {
-    pub fn new(result: Result<Vec<InternalArtifact>>) -> Result<ScanRspData> {
+    pub fn new(result: Result<Vec<ArtifactRecord>>) -> Result<ScanRspData> {
         Ok(match result {
-            Ok(arts) => ScanRspData {
+            Ok(records) => ScanRspData {
+                version: SCAN_RSP_VERSION,
                 success: true,
                 message: String::from(""),
-                good_json: to_string(&GoodFormat::new(&arts))?,
+                good_json: to_string(
+                    &GoodFormat::new(&recognized_artifacts(&records))
+                        .with_indices(&recognized_indices(&records))
+                        .with_main_stat_values(true),
+                )?,
+                unrecognized_indices: unrecognized_indices(&records),
+                art_count: ScanSummary::new(&records).art_count,
             },
             Err(e) => ScanRspData {
+                version: SCAN_RSP_VERSION,
                 success: false,
                 message: e.to_string(),
                 good_json: String::from(""),
+                unrecognized_indices: Vec::new(),
+                art_count: 0,
             },
         })
     }
-    pub fn packet(result: Result<Vec<InternalArtifact>>) -> Result<Packet> {
+    pub fn packet(result: Result<Vec<ArtifactRecord>>) -> Result<Packet> {
         Ok(Packet::ScanRsp(Self::new(result)?))
     }
 }
@@ -66,26 +102,78 @@ pub struct LockReqData {
 pub struct LockRspData {
     pub success: bool,
     pub message: String,
+    #[serde(default)]
+    pub report: Option<LockReport>,
 }
 
 impl LockRspData {
-    pub fn new(result: Result<()>) -> Result<LockRspData> {
+    pub fn new(result: Result<LockReport>) -> Result<LockRspData> {
         Ok(match result {
-            Ok(()) => LockRspData {
-                success: true,
-                message: String::from(""),
+            Ok(report) => LockRspData {
+                success: report.ok(),
+                message: if report.ok() {
+                    String::from("")
+                } else {
+                    format!(
+                        "failed: {:?}, mismatched: {:?}, unverified: {:?}",
+                        report.failed, report.mismatched, report.unverified
+                    )
+                },
+                report: Some(report),
             },
             Err(e) => LockRspData {
                 success: false,
                 message: e.to_string(),
+                report: None,
             },
         })
     }
-    pub fn packet(result: Result<()>) -> Result<Packet> {
+    pub fn packet(result: Result<LockReport>) -> Result<Packet> {
         Ok(Packet::LockRsp(Self::new(result)?))
     }
 }
 
+// simulates a lock request against a scan, good_json, unrecognized_indices
+// and art_count are those of a ScanRsp
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct DryRunReqData {
+    pub indices: Option<Vec<u32>>,
+    pub lock_json: Option<String>,
+    pub good_json: String,
+    #[serde(default)]
+    pub unrecognized_indices: Vec<u32>,
+    pub art_count: Option<u32>,
+    // e.g. "1920x1080", for the page count
+    pub window_size: Option<String>,
+}
+
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct DryRunRspData {
+    pub success: bool,
+    pub message: String,
+    pub report: Option<DryRunReport>,
+}
+
+impl DryRunRspData {
+    pub fn new(result: Result<DryRunReport>) -> Result<DryRunRspData> {
+        Ok(match result {
+            Ok(report) => DryRunRspData {
+                success: true,
+                message: String::from(""),
+                report: Some(report),
+            },
+            Err(e) => DryRunRspData {
+                success: false,
+                message: e.to_string(),
+                report: None,
+            },
+        })
+    }
+    pub fn packet(result: Result<DryRunReport>) -> Result<Packet> {
+        Ok(Packet::DryRunRsp(Self::new(result)?))
+    }
+}
+
 #[derive(Debug, Deserialize, Serialize)]
 #[serde(tag = "cmd", content = "data")]
 pub enum Packet {
@@ -94,6 +182,8 @@ pub enum Packet {
     ScanRsp(ScanRspData),
     LockReq(LockReqData),
     LockRsp(LockRspData),
+    DryRunReq(DryRunReqData),
+    DryRunRsp(DryRunRspData),
 }
 
 impl Packet {
@@ -107,6 +197,8 @@ impl Packet {
             Self::ScanRsp(_) => "ScanRsp",
             Self::LockReq(_) => "LockReq",
             Self::LockRsp(_) => "LockRsp",
+            Self::DryRunReq(_) => "DryRunReq",
+            Self::DryRunRsp(_) => "DryRunRsp",
         }
     }
 }